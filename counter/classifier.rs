@@ -0,0 +1,444 @@
+use super::language::{is_line_anchored, BlockComment, Language, Quote};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    Code,
+    Comment,
+    /// Documentation: doc comments such as `///` or `/** */`, Perl POD and
+    /// Python docstrings.
+    DocComment,
+    /// Code and a comment on the same line, e.g. `let x = 1; // note`.
+    Mixed,
+}
+
+/// Classifies the lines of one file in order, carrying block comment and
+/// multi-line string state from line to line.
+pub struct LineClassifier {
+    language: Option<&'static Language>,
+    /// Block comment the classifier is in, if any.
+    block_comment: Option<BlockComment>,
+    /// Nesting depth of `block_comment`; always 1 for non-nesting comments.
+    block_comment_depth: usize,
+    /// Whether `block_comment` is a documentation comment.
+    block_comment_doc: bool,
+    /// String literal the classifier is in, if any.
+    string: Option<&'static Quote>,
+    /// Whether `string` is a docstring.
+    string_doc: bool,
+    /// Number of lines classified so far.
+    line_number: usize,
+    /// Line on which the open block comment started.
+    block_comment_line: usize,
+    /// For languages with docstrings: whether a string starting the next
+    /// statement is a docstring. True at the top of the file and after a
+    /// `def` or `class` header.
+    docstring_allowed: bool,
+    /// Inside a `def` or `class` header, which may span several lines.
+    in_definition_header: bool,
+    /// Open brackets in the current statement, used to find the end of a
+    /// multi-line definition header.
+    bracket_depth: usize,
+}
+
+impl LineClassifier {
+    pub fn new(language: Option<&'static Language>) -> Self {
+        LineClassifier {
+            language,
+            block_comment: None,
+            block_comment_depth: 0,
+            block_comment_doc: false,
+            string: None,
+            string_doc: false,
+            line_number: 0,
+            block_comment_line: 0,
+            docstring_allowed: true,
+            in_definition_header: false,
+            bracket_depth: 0,
+        }
+    }
+
+    /// If the input ended inside a block comment, returns the 1-based line
+    /// on which that comment started.
+    pub fn unterminated_block_comment(&self) -> Option<usize> {
+        self.block_comment.map(|_| self.block_comment_line)
+    }
+
+    /// Classifies `line`, which must not contain the line terminator.
+    pub fn classify(&mut self, line: &str) -> LineKind {
+        self.line_number += 1;
+        let line = line.trim();
+        if line.is_empty() {
+            return LineKind::Blank;
+        }
+        let Some(language) = self.language else {
+            return LineKind::Code;
+        };
+
+        if language.docstrings && self.string.is_none() && self.block_comment.is_none() && is_definition(line) {
+            self.in_definition_header = true;
+        }
+
+        let mut has_code = false;
+        let mut has_comment = false;
+        let mut has_doc = false;
+        let mut last_code = None;
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+
+            if let Some(comment) = self.block_comment {
+                if self.block_comment_doc {
+                    has_doc = true;
+                } else {
+                    has_comment = true;
+                }
+                if comment.nested {
+                    i += self.step_nested_comment(rest, &comment);
+                    continue;
+                }
+                match find_end(rest, comment.end, i) {
+                    Some(pos) => {
+                        i += pos + comment.end.len();
+                        self.block_comment = None;
+                    }
+                    None => i = line.len(),
+                }
+                continue;
+            }
+
+            if let Some(quote) = self.string {
+                if self.string_doc {
+                    has_doc = true;
+                } else {
+                    has_code = true;
+                }
+                match find_string_end(rest, quote) {
+                    Some(pos) => {
+                        i += pos + quote.end.len();
+                        self.string = None;
+                    }
+                    None => {
+                        i = line.len();
+                        if !quote.multiline {
+                            self.string = None;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // Block delimiters are checked first since some extend a line
+            // comment prefix, e.g. Lua's `--[[` and `--`.
+            if let Some(comment) = language.block_comment_at(rest, i) {
+                // `/**/` is an empty comment, not the start of `/** ... */`.
+                let empty = rest[comment.start.len()..].starts_with(comment.end);
+                self.block_comment = Some(comment);
+                self.block_comment_depth = 1;
+                self.block_comment_doc = language.is_doc_comment(rest) && !empty;
+                self.block_comment_line = self.line_number;
+                // The delimiter alone may fill the line, e.g. Perl's `=pod`.
+                if self.block_comment_doc {
+                    has_doc = true;
+                } else {
+                    has_comment = true;
+                }
+                i += comment.start.len();
+                continue;
+            }
+
+            let prev = line[..i].chars().next_back();
+            if language.line_comment_at(rest, i, prev) {
+                if language.is_doc_comment(rest) {
+                    has_doc = true;
+                } else {
+                    has_comment = true;
+                }
+                break;
+            }
+
+            if let Some(quote) = language.quote_at(rest, prev) {
+                self.string = Some(quote);
+                self.string_doc = language.docstrings && self.docstring_allowed && !has_code;
+                if self.string_doc {
+                    has_doc = true;
+                } else {
+                    has_code = true;
+                }
+                self.docstring_allowed = false;
+                i += quote.start.len();
+                continue;
+            }
+
+            let c = rest.chars().next().unwrap();
+            if !c.is_whitespace() {
+                has_code = true;
+                last_code = Some(c);
+                match c {
+                    '(' | '[' | '{' => self.bracket_depth += 1,
+                    ')' | ']' | '}' => self.bracket_depth = self.bracket_depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            i += c.len_utf8();
+        }
+
+        if language.docstrings && has_code {
+            self.update_docstring_state(last_code);
+        }
+
+        match (has_code, has_comment || has_doc) {
+            (true, true) => LineKind::Mixed,
+            (true, false) => LineKind::Code,
+            _ if has_doc => LineKind::DocComment,
+            _ => LineKind::Comment,
+        }
+    }
+
+    /// Advances through a nesting block comment by one token, adjusting the
+    /// depth, and returns the number of bytes consumed.
+    fn step_nested_comment(&mut self, rest: &str, comment: &BlockComment) -> usize {
+        if rest.starts_with(comment.end) {
+            self.block_comment_depth -= 1;
+            if self.block_comment_depth == 0 {
+                self.block_comment = None;
+            }
+            return comment.end.len();
+        }
+        if rest.starts_with(comment.start) {
+            self.block_comment_depth += 1;
+            return comment.start.len();
+        }
+        rest.chars().next().map_or(rest.len(), char::len_utf8)
+    }
+
+    /// A docstring may follow a definition header once its closing `:` is
+    /// reached; any other code uses up the chance.
+    fn update_docstring_state(&mut self, last_code: Option<char>) {
+        if !self.in_definition_header {
+            self.docstring_allowed = false;
+        } else if self.bracket_depth == 0 && self.string.is_none() {
+            self.in_definition_header = false;
+            self.docstring_allowed = last_code == Some(':');
+        }
+    }
+}
+
+fn is_definition(line: &str) -> bool {
+    ["def ", "async def ", "class "].iter().any(|keyword| line.starts_with(keyword))
+}
+
+/// Finds the block comment terminator `end` in `rest`, which starts at
+/// column `column` of the line. Line-anchored terminators such as `=cut`
+/// only count at the start of a line.
+fn find_end(rest: &str, end: &str, column: usize) -> Option<usize> {
+    if is_line_anchored(end) {
+        return (column == 0 && rest.starts_with(end)).then_some(0);
+    }
+    rest.find(end)
+}
+
+/// Finds the closing delimiter of `quote` in `rest`, skipping escaped
+/// characters.
+fn find_string_end(rest: &str, quote: &Quote) -> Option<usize> {
+    let mut chars = rest.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        if rest[pos..].starts_with(quote.end) {
+            return Some(pos);
+        }
+        if Some(c) == quote.escape {
+            chars.next();
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::language::LANGUAGES;
+    use super::*;
+    use LineKind::*;
+
+    fn by_name(name: &str) -> Option<&'static Language> {
+        LANGUAGES.iter().find(|language| language.name == name)
+    }
+
+    fn classify(language: &str, text: &str) -> Vec<LineKind> {
+        let mut classifier = LineClassifier::new(by_name(language));
+        text.lines().map(|line| classifier.classify(line)).collect()
+    }
+
+    #[test]
+    fn blank_code_and_line_comments() {
+        let text = "fn main() {\n\n    // note\n    run();\n}\n";
+        assert_eq!(classify("Rust", text), [Code, Blank, Comment, Code, Code]);
+    }
+
+    #[test]
+    fn code_with_trailing_comment_is_mixed() {
+        assert_eq!(classify("C", "int x = 1; // one"), [Mixed]);
+        assert_eq!(classify("C", "int x = 1; /* one */"), [Mixed]);
+        assert_eq!(classify("C", "/* one */ int x = 1;"), [Mixed]);
+    }
+
+    #[test]
+    fn block_comment_closed_on_same_line() {
+        assert_eq!(classify("C", "/* only a comment */"), [Comment]);
+        assert_eq!(classify("C", "/* a */ /* b */"), [Comment]);
+    }
+
+    #[test]
+    fn multiple_block_comments_with_code_between() {
+        assert_eq!(classify("C", "/* a */ x = 1; /* b */"), [Mixed]);
+        assert_eq!(classify("C", "x /* a */ + /* b */ y;"), [Mixed]);
+    }
+
+    #[test]
+    fn multi_line_block_comment() {
+        let text = "/*\n * text\n\n */\nint x;\n";
+        assert_eq!(classify("C", text), [Comment, Comment, Blank, Comment, Code]);
+    }
+
+    #[test]
+    fn code_after_block_comment_ends() {
+        let text = "/* start\nend */ int x;\n";
+        assert_eq!(classify("C", text), [Comment, Mixed]);
+    }
+
+    #[test]
+    fn unterminated_block_comment_at_eof() {
+        let mut classifier = LineClassifier::new(by_name("C"));
+        for line in ["int x;", "", "/* open", "still open"] {
+            classifier.classify(line);
+        }
+        assert_eq!(classifier.unterminated_block_comment(), Some(3));
+    }
+
+    #[test]
+    fn closed_block_comment_is_not_reported() {
+        let mut classifier = LineClassifier::new(by_name("C"));
+        for line in ["/* open", "closed */ int x;"] {
+            classifier.classify(line);
+        }
+        assert_eq!(classifier.unterminated_block_comment(), None);
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        let text = "/* outer /* inner */ still comment */ code();\n";
+        assert_eq!(classify("Rust", text), [Mixed]);
+        let text = "/* outer\n/* inner */\nstill comment\n*/\ncode();\n";
+        assert_eq!(classify("Rust", text), [Comment, Comment, Comment, Comment, Code]);
+    }
+
+    #[test]
+    fn non_nesting_block_comment_ends_at_first_end() {
+        let text = "/* outer /* inner */ code();\n";
+        assert_eq!(classify("C", text), [Mixed]);
+    }
+
+    #[test]
+    fn unterminated_nested_comment() {
+        let mut classifier = LineClassifier::new(by_name("Rust"));
+        for line in ["/* a /* b */", "still open"] {
+            classifier.classify(line);
+        }
+        assert_eq!(classifier.unterminated_block_comment(), Some(1));
+    }
+
+    #[test]
+    fn doc_comments() {
+        let text = "/// Docs.\n//! Crate docs.\n//// Not docs.\n// Plain.\n";
+        assert_eq!(classify("Rust", text), [DocComment, DocComment, Comment, Comment]);
+        let text = "/**\n * Docs.\n */\n/***/\n/**/\n";
+        assert_eq!(classify("Java", text), [DocComment, DocComment, DocComment, Comment, Comment]);
+    }
+
+    #[test]
+    fn perl_pod_lines_are_all_documentation() {
+        let text = "=pod\n\ndoc\n\n=cut\nprint 1;\n";
+        assert_eq!(classify("Perl", text), [DocComment, Blank, DocComment, Blank, DocComment, Code]);
+        let text = "=begin comment\ntext\n=cut\n";
+        assert_eq!(classify("Perl", text), [Comment, Comment, Comment]);
+    }
+
+    #[test]
+    fn python_docstrings() {
+        let text = "\"\"\"Module docs.\"\"\"\n\ndef f(a,\n      b):\n    \"\"\"Function\n    docs.\n    \"\"\"\n    return 1\n";
+        assert_eq!(
+            classify("Python", text),
+            [DocComment, Blank, Code, Code, DocComment, DocComment, DocComment, Code]
+        );
+        let text = "def f():\n    r\"\"\"Doc with \\d.\"\"\"\n    return R'''\n    not docs'''\n";
+        assert_eq!(classify("Python", text), [Code, DocComment, Code, Code]);
+    }
+
+    #[test]
+    fn triple_quoted_strings_outside_docstring_positions_are_code() {
+        let text = "import os\n\"\"\"Not a docstring.\"\"\"\nx = \"\"\"\ntext\n\"\"\"\n";
+        assert_eq!(classify("Python", text), [Code, Code, Code, Code, Code]);
+        let text = "class A:\n    x = 1\n    \"\"\"Not a docstring.\"\"\"\n";
+        assert_eq!(classify("Python", text), [Code, Code, Code]);
+    }
+
+    #[test]
+    fn comment_markers_inside_strings_are_code() {
+        assert_eq!(classify("C", r#"char *url = "http://example.com";"#), [Code]);
+        assert_eq!(classify("C", r#"puts("/* not a comment */");"#), [Code]);
+        assert_eq!(classify("C", r#"puts("say \"//\" here"); // real"#), [Mixed]);
+        assert_eq!(classify("Python", "s = '# not a comment'"), [Code]);
+    }
+
+    #[test]
+    fn comment_markers_inside_raw_strings_are_code() {
+        assert_eq!(classify("Rust", r##"let re = r#"a"//b"#;"##), [Code]);
+        assert_eq!(classify("Rust", r#"let path = r"C:\dir\"; // note"#), [Mixed]);
+        assert_eq!(classify("C++", r#"auto s = R"(/* raw */)";"#), [Code]);
+    }
+
+    #[test]
+    fn multi_line_strings_carry_over_lines() {
+        let text = "let s = \"first\n// inside\n/* still inside\";\n// after\n";
+        assert_eq!(classify("Rust", text), [Code, Code, Code, Comment]);
+    }
+
+    #[test]
+    fn comment_markers_inside_triple_quoted_strings_are_code() {
+        let text = "x = \"\"\"\n# inside\n\"\"\"\n# after\n";
+        assert_eq!(classify("Python", text), [Code, Code, Code, Comment]);
+    }
+
+    #[test]
+    fn rust_char_literals() {
+        assert_eq!(classify("Rust", "let c = '\"'; // quote"), [Mixed]);
+        assert_eq!(classify("Rust", "let c = '\\''; // quote"), [Mixed]);
+        assert_eq!(classify("Rust", "let c = '\\u{1F600}'; // emoji"), [Mixed]);
+        assert_eq!(classify("Rust", "let c = '/'; let d = '*';"), [Code]);
+    }
+
+    #[test]
+    fn rust_lifetimes_do_not_open_char_literals() {
+        assert_eq!(classify("Rust", "fn f(x: &'static str) {} // c"), [Mixed]);
+        assert_eq!(classify("Rust", "static S: &'static str = \"a\"; /* c */"), [Mixed]);
+        assert_eq!(classify("Rust", "fn g<'a>(x: &'a str) -> &'a str { x } // c"), [Mixed]);
+        assert_eq!(classify("Rust", "'outer: loop { break 'outer; } // c"), [Mixed]);
+    }
+
+    #[test]
+    fn shell_comments_start_words_only() {
+        assert_eq!(classify("Shell", "echo ${#arr[@]}"), [Code]);
+        assert_eq!(classify("Shell", "echo a#b"), [Code]);
+        assert_eq!(classify("Shell", "echo hi # greet"), [Mixed]);
+        assert_eq!(classify("Shell", "# comment"), [Comment]);
+        assert_eq!(classify("YAML", "url: http://x/#anchor"), [Code]);
+        assert_eq!(classify("Makefile", "all: build\t# default"), [Mixed]);
+        assert_eq!(classify("Python", "x = 1#note"), [Mixed]);
+    }
+
+    #[test]
+    fn unknown_language_is_all_code() {
+        let mut classifier = LineClassifier::new(None);
+        assert_eq!(classifier.classify("// not a comment"), Code);
+        assert_eq!(classifier.classify("   "), Blank);
+    }
+}
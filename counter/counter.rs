@@ -1,228 +1,848 @@
+mod classifier;
+mod document;
+mod embedded;
+mod encoding;
+mod language;
+mod scanner;
+
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Read};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-#[derive(Default, Debug)]
-struct FileInfo {
-    filetype: String,
-    steps: usize,
-    blanks: usize,
-    comments: usize,
-    files: usize,
-    bytes: usize,
+use classifier::{LineClassifier, LineKind};
+use embedded::RegionSplitter;
+use encoding_rs::{Encoding, UTF_8};
+use language::{Embedding, Language};
+use scanner::{LineScanner, Lines, SliceLines};
+use serde::{Deserialize, Serialize};
+
+pub use document::SCHEMA_VERSION;
+
+/// Line counts of one file type, or of a single file.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub filetype: String,
+    pub steps: usize,
+    pub code: usize,
+    pub blanks: usize,
+    pub comments: usize,
+    /// Lines of documentation comments and docstrings. They are included in
+    /// `comments`.
+    pub doc_comments: usize,
+    /// Lines holding both code and a comment. They are included in `code`.
+    pub mixed: usize,
+    pub files: usize,
+    /// Bytes of the counted lines as UTF-8, without surrounding whitespace,
+    /// plus one per line break. For a file in another encoding this is the
+    /// size of the decoded text, not of the file.
+    pub bytes: usize,
+    /// Lines of other languages embedded in these files, such as the
+    /// `<script>` elements of HTML, one entry per language. For these entries
+    /// `files` is the number of parent files containing the language. Every
+    /// line of a notebook belongs to a cell, so its own counts, `bytes`
+    /// included, are zero.
+    #[serde(default, serialize_with = "document::sorted")]
+    pub children: Vec<FileInfo>,
+    /// Encoding a single file was read with, e.g. `UTF-8` or `Shift_JIS`,
+    /// marked `(lossy)` if some bytes could not be decoded. `None` for
+    /// totals over several files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Problems found while counting a single file, such as a block comment
+    /// that is never closed. Not written to the JSON, YAML or TOML output.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Default, Debug)]
-struct CntResult {
-    info: Vec<FileInfo>,
-    input_path: String,
-    all_steps: usize,
-    all_blanks: usize,
-    all_comments: usize,
-    all_files: usize,
-    all_bytes: usize,
+/// Counts per file type and their totals. Serializes as the versioned
+/// document of the JSON, YAML and TOML output.
+#[derive(Default, Debug, Deserialize)]
+#[serde(try_from = "document::StoredDocument")]
+pub struct CntResult {
+    pub info: Vec<FileInfo>,
+    pub input_path: String,
+    pub all_steps: usize,
+    pub all_code: usize,
+    pub all_blanks: usize,
+    pub all_comments: usize,
+    pub all_doc_comments: usize,
+    pub all_mixed: usize,
+    pub all_files: usize,
+    pub all_bytes: usize,
+    /// Per-file records sorted by path, empty unless
+    /// [`CountOptions::keep_files`] was set.
+    pub files: Vec<FileRecord>,
+    /// Files that could not be counted and problems found in the counted
+    /// ones, sorted. Not written to the JSON, YAML or TOML output.
+    pub warnings: Vec<String>,
 }
 
+/// Line counts of one file, kept when [`CountOptions::keep_files`] is set.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FileRecord {
+    pub path: String,
+    /// Counts of the file; `filetype` is its language.
+    #[serde(flatten)]
+    pub info: FileInfo,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct CountOptions {
+    /// Keep a [`FileRecord`] for every counted file in [`CntResult::files`].
+    pub keep_files: bool,
+    /// Number of worker threads; `None` uses the available parallelism.
+    pub threads: Option<usize>,
+}
+
+/// Counts collected by one worker, merged with the others' at the end.
+#[derive(Default)]
+struct Tally {
+    by_type: HashMap<String, FileInfo>,
+    /// Per-file records, only collected when asked for.
+    records: Option<Vec<FileRecord>>,
+    warnings: Vec<String>,
+}
+
+/// Size of the buffer files are read through; larger files are mapped.
 const MAX_CAPACITY: usize = 1024 * 1024;
-const CONCURRENCY_THRESHOLD: usize = 6;
 
-fn count(files: Vec<String>, input_path: String) -> io::Result<CntResult> {
-    let mut result = CntResult {
-        input_path: input_path.clone(),
-        ..Default::default()
+/// Counts the lines of `files`, grouped by file type, with the same worker
+/// pool as [`count_from`]. `input_path` is only recorded in the result. Files
+/// that cannot be read are skipped and noted in [`CntResult::warnings`].
+pub fn count(files: Vec<String>, input_path: String, options: &CountOptions) -> CntResult {
+    let threads = worker_count(options).min(files.len()).max(1);
+    let queue = FileQueue::List {
+        files,
+        next: AtomicUsize::new(0),
     };
-    let buf_map: Arc<Mutex<HashMap<String, FileInfo>>> = Arc::new(Mutex::new(HashMap::new()));
-    let len_files = files.len();
+    run_workers(queue, threads, options).into_result(input_path)
+}
 
-    if len_files >= CONCURRENCY_THRESHOLD {
-        let chunk_size = (len_files + 2) / 3;
-        let chunks: Vec<Vec<String>> = files.chunks(chunk_size).map(|chunk| chunk.to_vec()).collect();
+/// Counts the files received from `files` while they are still being
+/// produced, e.g. by a directory walk on another thread, until every sender
+/// is dropped. Files that cannot be read are skipped and noted in
+/// [`CntResult::warnings`].
+pub fn count_from(files: Receiver<String>, input_path: String, options: &CountOptions) -> CntResult {
+    let queue = FileQueue::Channel(Mutex::new(files));
+    run_workers(queue, worker_count(options), options).into_result(input_path)
+}
 
-        let mut handles = vec![];
-        for chunk in chunks {
-            let buf_map = Arc::clone(&buf_map);
-            let handle = thread::spawn(move || process_files(chunk, buf_map));
-            handles.push(handle);
-        }
+fn worker_count(options: &CountOptions) -> usize {
+    options
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1)
+}
 
-        for handle in handles {
-            handle.join().unwrap();
-        }
-    } else {
-        for file in files {
-            process_file(file, &buf_map)?;
-        }
+fn run_workers(queue: FileQueue, threads: usize, options: &CountOptions) -> Tally {
+    let queue = Arc::new(queue);
+    let mut handles = vec![];
+    for _ in 0..threads {
+        let queue = Arc::clone(&queue);
+        let keep_files = options.keep_files;
+        let handle = thread::spawn(move || process_files(&queue, keep_files));
+        handles.push(handle);
     }
 
-    let buf_map = buf_map.lock().unwrap();
-    for (_, file_info) in buf_map.iter() {
-        result.info.push(file_info.clone());
+    let mut tally = Tally::new(options.keep_files);
+    for handle in handles {
+        tally.merge(handle.join().unwrap());
     }
-    result.assign_alls();
-    Ok(result)
+    tally
+}
+
+/// Where content being counted comes from, which determines its language.
+#[derive(Debug, Clone, Copy)]
+pub enum Source<'a> {
+    /// A file name, real or virtual. The language is detected from the name
+    /// and, failing that, from a `#!` line or modeline in the content.
+    Path(&'a Path),
+    /// A language name such as `Rust`, or an alias such as `py` or `sh`.
+    Language(&'a str),
+}
+
+thread_local! {
+    /// Read buffer reused for the files and readers counted on this thread.
+    static BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+/// Counts the lines of a single file. `files` of the result is 1. Files
+/// larger than the read buffer are memory-mapped when the `mmap` feature is
+/// enabled. A binary file is an `InvalidData` error.
+pub fn count_file(file: &str) -> io::Result<FileInfo> {
+    count_text_file(file)?.ok_or_else(binary_content)
 }
 
-fn count_file(file: &str) -> io::Result<FileInfo> {
-    let mut info = FileInfo::default();
+/// Counts a file unless it is binary.
+fn count_text_file(file: &str) -> io::Result<Option<FileInfo>> {
     let path = Path::new(file);
     let file = File::open(path)?;
-    let mut scanner = io::BufReader::new(file);
 
-    info.filetype = ret_file_type(path);
+    #[cfg(feature = "mmap")]
+    if file.metadata()?.len() > MAX_CAPACITY as u64 {
+        // SAFETY: the map is only read while counting. If another process
+        // truncates the file meanwhile, reading may fault, the same trade-off
+        // other line counters and search tools make for large files.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        return count_lines(&mut SliceLines::new(&map), Source::Path(path));
+    }
+
+    scan(file, Source::Path(path))
+}
 
-    let mut in_block_comment = false;
-    for line in scanner.lines() {
-        let line = line?.trim().to_string();
-        info.steps += 1;
-        info.bytes += line.len() + 1; // +1 for newline character
+/// Counts the lines of in-memory content, e.g. a git blob or an editor buffer.
+/// Binary content is an `InvalidData` error.
+pub fn count_bytes(content: &[u8], source: Source) -> io::Result<FileInfo> {
+    count_lines(&mut SliceLines::new(content), source)?.ok_or_else(binary_content)
+}
 
-        if line.is_empty() {
-            info.blanks += 1;
-            continue;
+/// Counts the lines read from `reader`. An unknown language name is an
+/// `InvalidInput` error, binary content an `InvalidData` error.
+pub fn count_reader<R: BufRead>(reader: R, source: Source) -> io::Result<FileInfo> {
+    scan(reader, source)?.ok_or_else(binary_content)
+}
+
+/// Counts the lines read from `reader` through this thread's [`BUFFER`].
+fn scan<R: Read>(reader: R, source: Source) -> io::Result<Option<FileInfo>> {
+    let mut scanner = LineScanner::new(reader, BUFFER.take());
+    let info = count_lines(&mut scanner, source);
+    BUFFER.set(scanner.into_buffer());
+    info
+}
+
+fn binary_content() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "binary content")
+}
+
+/// Counts `lines` after working out their encoding. Content that is not
+/// plain UTF-8 is decoded as a whole first; invalid sequences are replaced,
+/// so every text file is counted. The encoding used is noted in the result.
+/// Returns `None` for binary content.
+fn count_lines(lines: &mut impl Lines, source: Source) -> io::Result<Option<FileInfo>> {
+    let head = lines.head()?;
+    let bom = Encoding::for_bom(head).is_some();
+    let Some(detected) = encoding::detect(head) else {
+        return Ok(None);
+    };
+
+    if detected == UTF_8 && !bom {
+        let (mut info, lossy) = count_text(lines, source)?;
+        info.encoding = Some(if lossy { "UTF-8 (lossy)" } else { "UTF-8" }.to_string());
+        return Ok(Some(info));
+    }
+
+    let content = lines.rest()?;
+    let (text, lossy) = detected.decode_with_bom_removal(&content);
+    let (mut info, _) = count_text(&mut SliceLines::new(text.as_bytes()), source)?;
+    let mut note = detected.name().to_string();
+    if bom {
+        note.push_str(" (BOM)");
+    }
+    if lossy {
+        note.push_str(" (lossy)");
+    }
+    info.encoding = Some(note);
+    Ok(Some(info))
+}
+
+/// Counts UTF-8 `lines`. Also returns whether any of them had to be decoded
+/// lossily.
+fn count_text(lines: &mut impl Lines, source: Source) -> io::Result<(FileInfo, bool)> {
+    let (language, filetype, origin) = match source {
+        Source::Path(path) => {
+            // Files without a recognised name or extension are identified by
+            // their `#!` line or an editor modeline.
+            let language = match language::from_path(path) {
+                Some(language) => Some(language),
+                None => language::from_head(&String::from_utf8_lossy(lines.head()?)),
+            };
+            (language, ret_file_type(path, language), path.display().to_string())
+        }
+        Source::Language(name) => {
+            let language = language::from_name(name).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("unknown language: {}", name))
+            })?;
+            (Some(language), language.name.to_string(), "<input>".to_string())
         }
+    };
+    let mut info = FileInfo {
+        filetype,
+        files: 1,
+        ..Default::default()
+    };
 
-        if is_single_comment(&line) {
-            info.comments += 1;
-            continue;
+    if language.is_some_and(|language| language.embedded == Some(Embedding::Notebook)) {
+        let content = lines.rest()?;
+        let content = String::from_utf8_lossy(&content);
+        let lossy = matches!(content, Cow::Owned(_));
+        match embedded::notebook_cells(&content) {
+            Ok(cells) => {
+                for (index, cell) in cells.iter().enumerate() {
+                    let mut classifier = LineClassifier::new(Some(cell.language));
+                    let child = info.child(cell.language.name);
+                    for line in &cell.lines {
+                        child.tally(classifier.classify(line), line.trim());
+                    }
+                    let origin = format!("{} (cell {})", origin, index + 1);
+                    check_block_comments(&classifier, &origin, 0, &mut info.warnings);
+                }
+                return Ok((info, lossy));
+            }
+            Err(err) => {
+                info.warnings.push(format!("{}: not a valid notebook, counted as plain text: {}", origin, err));
+                let mut lines = SliceLines::new(content.as_bytes());
+                tally_lines(&mut info, language, &mut lines, &origin)?;
+                return Ok((info, lossy));
+            }
         }
+    }
+
+    let lossy = tally_lines(&mut info, language, lines, &origin)?;
+    Ok((info, lossy))
+}
 
-        if is_begin_block_comments(&line) {
-            in_block_comment = true;
-            info.comments += 1;
+/// Counts `lines` into `info`, splitting off the regions of embedded
+/// languages into `info.children`. Returns whether any line was not valid
+/// UTF-8.
+fn tally_lines(
+    info: &mut FileInfo,
+    language: Option<&'static Language>,
+    lines: &mut impl Lines,
+    origin: &str,
+) -> io::Result<bool> {
+    let mut lossy = false;
+    let mut classifier = LineClassifier::new(language);
+    let mut splitter = language.and_then(|language| language.embedded).map(RegionSplitter::new);
+    // Classifier of the embedded region being counted, restarted per region,
+    // and the number of lines before the region.
+    let mut region: Option<(&'static Language, LineClassifier, usize)> = None;
+    let mut line_number = 0;
+
+    while let Some(line) = lines.next_line()? {
+        // Borrows the line unless it is not valid UTF-8.
+        let line = String::from_utf8_lossy(line);
+        lossy |= matches!(line, Cow::Owned(_));
+        let line = line.trim();
+        line_number += 1;
+        let child = splitter.as_mut().and_then(|splitter| splitter.route(line));
+        let Some(child) = child else {
+            if let Some((_, region_classifier, offset)) = region.take() {
+                check_block_comments(&region_classifier, origin, offset, &mut info.warnings);
+            }
+            info.tally(classifier.classify(line), line);
             continue;
-        }
+        };
 
-        if in_block_comment {
-            info.comments += 1;
-            if is_end_block_comments(&line) {
-                in_block_comment = false;
+        if region.as_ref().is_none_or(|(language, _, _)| language.name != child.name) {
+            if let Some((_, region_classifier, offset)) = region.take() {
+                check_block_comments(&region_classifier, origin, offset, &mut info.warnings);
             }
+            region = Some((child, LineClassifier::new(Some(child)), line_number - 1));
         }
+        let (_, region_classifier, _) = region.as_mut().unwrap();
+        info.child(child.name).tally(region_classifier.classify(line), line);
+    }
+
+    if let Some((_, region_classifier, offset)) = &region {
+        check_block_comments(region_classifier, origin, *offset, &mut info.warnings);
+    }
+    check_block_comments(&classifier, origin, 0, &mut info.warnings);
+    Ok(lossy)
+}
+
+/// Notes a block comment `classifier` was left in. Its lines are numbered
+/// from 1 after `offset` lines of `origin`.
+fn check_block_comments(classifier: &LineClassifier, origin: &str, offset: usize, warnings: &mut Vec<String>) {
+    if let Some(line) = classifier.unterminated_block_comment() {
+        warnings.push(format!("{}:{}: block comment is never closed", origin, offset + line));
     }
-    Ok(info)
 }
 
-fn process_files(files: Vec<String>, buf_map: Arc<Mutex<HashMap<String, FileInfo>>>) {
-    for file in files {
-        if let Err(err) = process_file(file, &buf_map) {
-            eprintln!("Failed to count lines in file {}: {}", file, err);
+/// Files waiting to be counted. Workers take the next file as soon as they
+/// are done with one, so a few large files do not hold up the others.
+enum FileQueue {
+    List { files: Vec<String>, next: AtomicUsize },
+    /// Files still being produced; `pop` waits for the next one.
+    Channel(Mutex<Receiver<String>>),
+}
+
+impl FileQueue {
+    fn pop(&self) -> Option<String> {
+        match self {
+            FileQueue::List { files, next } => files.get(next.fetch_add(1, Ordering::Relaxed)).cloned(),
+            FileQueue::Channel(receiver) => receiver.lock().unwrap().recv().ok(),
         }
     }
 }
 
-fn process_file(file: String, buf_map: &Arc<Mutex<HashMap<String, FileInfo>>>) -> io::Result<()> {
-    let file_info = count_file(&file)?;
-    let mut buf_map = buf_map.lock().unwrap();
+/// Counts files from `queue` until it is empty. Files that could not be
+/// counted are noted in the warnings of the returned tally.
+fn process_files(queue: &FileQueue, keep_files: bool) -> Tally {
+    let mut tally = Tally::new(keep_files);
+    while let Some(file) = queue.pop() {
+        if let Err(err) = process_file(file.clone(), &mut tally) {
+            tally.warnings.push(format!("Failed to count lines in file {}: {}", file, err));
+        }
+    }
+    tally
+}
 
-    let entry = buf_map.entry(file_info.filetype.clone()).or_insert_with(FileInfo::default);
-    entry.steps += file_info.steps;
-    entry.blanks += file_info.blanks;
-    entry.comments += file_info.comments;
-    entry.bytes += file_info.bytes;
-    entry.files += 1;
+/// Counts `file` into `tally`. Binary files are skipped.
+fn process_file(file: String, tally: &mut Tally) -> io::Result<()> {
+    let Some(file_info) = count_text_file(&file)? else {
+        return Ok(());
+    };
+    tally.add(&file_info);
+    tally.warnings.extend(file_info.warnings.iter().cloned());
+    if let Some(records) = &mut tally.records {
+        records.push(FileRecord {
+            path: file,
+            info: file_info,
+        });
+    }
 
     Ok(())
 }
 
-fn ret_file_type(path: &Path) -> String {
+impl Tally {
+    fn new(keep_files: bool) -> Self {
+        Tally {
+            by_type: HashMap::new(),
+            records: keep_files.then(Vec::new),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Adds `info` to the entry for its file type.
+    fn add(&mut self, info: &FileInfo) {
+        let entry = self.by_type.entry(info.filetype.clone()).or_insert_with(|| FileInfo {
+            filetype: info.filetype.clone(),
+            ..Default::default()
+        });
+        entry.merge(info);
+    }
+
+    fn into_result(mut self, input_path: String) -> CntResult {
+        self.warnings.sort();
+        let mut result = CntResult {
+            input_path,
+            info: self.by_type.into_values().collect(),
+            warnings: self.warnings,
+            ..Default::default()
+        };
+        if let Some(mut records) = self.records {
+            records.sort_by(|a, b| a.path.cmp(&b.path));
+            result.files = records;
+        }
+        result.assign_alls();
+        result
+    }
+
+    fn merge(&mut self, other: Tally) {
+        for info in other.by_type.values() {
+            self.add(info);
+        }
+        if let (Some(records), Some(other_records)) = (&mut self.records, other.records) {
+            records.extend(other_records);
+        }
+        self.warnings.extend(other.warnings);
+    }
+}
+
+/// Returns the canonical language name of `path`, falling back to the raw
+/// extension (or file name) for files not in the language registry.
+fn ret_file_type(path: &Path, language: Option<&Language>) -> String {
+    if let Some(language) = language {
+        return language.name.to_string();
+    }
     match path.extension() {
         Some(ext) => ext.to_string_lossy().to_string(),
-        None => path.file_name().unwrap().to_string_lossy().to_string(),
+        None => path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().to_string(),
+    }
+}
+
+impl FileInfo {
+    fn tally(&mut self, kind: LineKind, line: &str) {
+        self.steps += 1;
+        self.bytes += line.len() + 1; // +1 for newline character
+
+        match kind {
+            LineKind::Blank => self.blanks += 1,
+            LineKind::Comment => self.comments += 1,
+            LineKind::DocComment => {
+                self.comments += 1;
+                self.doc_comments += 1;
+            }
+            LineKind::Code => self.code += 1,
+            LineKind::Mixed => {
+                self.code += 1;
+                self.mixed += 1;
+            }
+        }
+    }
+
+    /// These counts with those of the embedded languages added in, without
+    /// the per-language breakdown.
+    pub fn total(&self) -> FileInfo {
+        let mut total = FileInfo {
+            filetype: self.filetype.clone(),
+            files: self.files,
+            encoding: self.encoding.clone(),
+            ..Default::default()
+        };
+        for counts in std::iter::once(self).chain(&self.children) {
+            total.steps += counts.steps;
+            total.code += counts.code;
+            total.blanks += counts.blanks;
+            total.comments += counts.comments;
+            total.doc_comments += counts.doc_comments;
+            total.mixed += counts.mixed;
+            total.bytes += counts.bytes;
+        }
+        total
+    }
+
+    /// The entry for an embedded language, created on first use.
+    fn child(&mut self, filetype: &str) -> &mut FileInfo {
+        let index = match self.children.iter().position(|child| child.filetype == filetype) {
+            Some(index) => index,
+            None => {
+                self.children.push(FileInfo {
+                    filetype: filetype.to_string(),
+                    files: 1,
+                    ..Default::default()
+                });
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+
+    /// Adds the counts of `other`, including its embedded languages, to
+    /// `self`. The file types are not compared; `self` keeps its own.
+    pub fn merge(&mut self, other: &FileInfo) {
+        self.steps += other.steps;
+        self.code += other.code;
+        self.blanks += other.blanks;
+        self.comments += other.comments;
+        self.doc_comments += other.doc_comments;
+        self.mixed += other.mixed;
+        self.files += other.files;
+        self.bytes += other.bytes;
+
+        for other_child in &other.children {
+            match self.children.iter_mut().find(|child| child.filetype == other_child.filetype) {
+                Some(child) => child.merge(other_child),
+                None => self.children.push(other_child.clone()),
+            }
+        }
     }
 }
 
 impl CntResult {
+    /// Serializes the result as JSON, following schema [`SCHEMA_VERSION`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("counts serialize as JSON")
+    }
+
+    /// Serializes the result as YAML, with the same fields as
+    /// [`CntResult::to_json`].
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).expect("counts serialize as YAML")
+    }
+
+    /// Serializes the result as TOML, with the same fields as
+    /// [`CntResult::to_json`]. File types and files are arrays of tables.
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("counts serialize as TOML")
+    }
+
+    /// Loads a result saved with [`CntResult::to_json`].
+    pub fn from_json(input: &str) -> io::Result<CntResult> {
+        document::from_json(input)
+    }
+
+    /// The totals over all file types as a single entry, with an empty
+    /// `filetype`.
+    pub fn totals(&self) -> FileInfo {
+        FileInfo {
+            steps: self.all_steps,
+            code: self.all_code,
+            blanks: self.all_blanks,
+            comments: self.all_comments,
+            doc_comments: self.all_doc_comments,
+            mixed: self.all_mixed,
+            files: self.all_files,
+            bytes: self.all_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Combines the result of another run into this one: entries of the same
+    /// file type are merged, per-file records and totals are added up and the
+    /// input paths are joined. Per-file records are only kept if both results
+    /// have them; a list covering one run alone would be incomplete.
+    pub fn merge(&mut self, other: &CntResult) {
+        for other_info in &other.info {
+            match self.info.iter_mut().find(|info| info.filetype == other_info.filetype) {
+                Some(info) => info.merge(other_info),
+                None => self.info.push(other_info.clone()),
+            }
+        }
+        if self.files.len() == self.all_files && other.files.len() == other.all_files {
+            self.files.extend(other.files.iter().cloned());
+            self.files.sort_by(|a, b| a.path.cmp(&b.path));
+        } else {
+            self.files.clear();
+        }
+        self.warnings.extend(other.warnings.iter().cloned());
+        self.warnings.sort();
+
+        if self.input_path.is_empty() {
+            self.input_path = other.input_path.clone();
+        } else if !other.input_path.is_empty() {
+            self.input_path = format!("{} {}", self.input_path, other.input_path);
+        }
+
+        self.all_steps += other.all_steps;
+        self.all_code += other.all_code;
+        self.all_blanks += other.all_blanks;
+        self.all_comments += other.all_comments;
+        self.all_doc_comments += other.all_doc_comments;
+        self.all_mixed += other.all_mixed;
+        self.all_files += other.all_files;
+        self.all_bytes += other.all_bytes;
+    }
+
+    /// Sums up the totals. Lines of embedded languages are included; `all_files`
+    /// counts each file once.
     fn assign_alls(&mut self) {
         for info in &self.info {
+            let info = info.total();
             self.all_steps += info.steps;
+            self.all_code += info.code;
             self.all_blanks += info.blanks;
             self.all_comments += info.comments;
+            self.all_doc_comments += info.doc_comments;
+            self.all_mixed += info.mixed;
             self.all_files += info.files;
-            self.all_bytes += info.bytes as i64;
-        }
-    }
-}
-
-lazy_static::lazy_static! {
-    static ref SINGLE_COMMENT_PREFIXES: HashMap<&'static str, ()> = {
-        let mut m = HashMap::new();
-        m.insert("//", ());
-        m.insert("///", ());
-        m.insert("#", ());
-        m.insert("!", ());
-        m.insert("--", ());
-        m.insert("%", ());
-        m.insert(";", ());
-        m.insert("#;", ());
-        m.insert("⍝", ());
-        m.insert("rem ", ());
-        m.insert("::", ());
-        m.insert(":  ", ());
-        m.insert("'", ());
-        m
-    };
+            self.all_bytes += info.bytes;
+        }
+    }
+}
 
-    static ref BLOCK_COMMENT_PREFIXES: HashMap<&'static str, ()> = {
-        let mut m = HashMap::new();
-        m.insert("/*", ());
-        m.insert("/**", ());
-        m.insert("--", ());
-        m.insert("<!--", ());
-        m.insert("<%--", ());
-        m.insert("////", ());
-        m.insert("/+", ());
-        m.insert("/++", ());
-        m.insert("(*", ());
-        m.insert("{-", ());
-        m.insert("\"\"\"", ());
-        m.insert("'''", ());
-        m.insert("#=", ());
-        m.insert("--[[", ());
-        m.insert("%{", ());
-        m.insert("#[", ());
-        m.insert("=pod", ());
-        m.insert("=comment", ());
-        m.insert("=begin", ());
-        m.insert("<#", ());
-        m.insert("#|", ());
-        m
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
 
-    static ref BLOCK_COMMENT_SUFFIXES: HashMap<&'static str, ()> = {
-        let mut m = HashMap::new();
-        m.insert("*/", ());
-        m.insert("**/", ());
-        m.insert("-->", ());
-        m.insert("--%>", ());
-        m.insert("--", ());
-        m.insert("+/", ());
-        m.insert("*)", ());
-        m.insert("-}", ());
-        m.insert("%}", ());
-        m.insert("=#", ());
-        m.insert("=cut", ());
-        m.insert("=end", ());
-        m.insert("--]]", ());
-        m.insert("]#", ());
-        m.insert("#>", ());
-        m.insert("\"\"\"", ());
-        m.insert("'''", ());
-        m.insert("|#", ());
-        m
-    };
-}
+    fn html_with_script() -> FileInfo {
+        let content = "<p>x</p>\n<script>\nlet a = 1; // one\n</script>\n";
+        count_bytes(content.as_bytes(), Source::Language("html")).unwrap()
+    }
 
-fn is_single_comment(line: &str) -> bool {
-    SINGLE_COMMENT_PREFIXES.keys().any(|&prefix| line.starts_with(prefix))
-}
+    #[test]
+    fn counts_bytes_in_a_named_language() {
+        let info = count_bytes(b"// note\n\nfn main() {}\n", Source::Language("rs")).unwrap();
+        assert_eq!(info.filetype, "Rust");
+        assert_eq!((info.steps, info.code, info.blanks, info.comments), (3, 1, 1, 1));
+        assert_eq!(info.encoding.as_deref(), Some("UTF-8"));
+    }
 
-fn is_begin_block_comments(line: &str) -> bool {
-    BLOCK_COMMENT_PREFIXES.keys().any(|&prefix| line.starts_with(prefix))
-}
+    #[test]
+    fn detects_language_from_path_and_shebang() {
+        let info = count_bytes(b"x = 1\n", Source::Path(Path::new("a/b.py"))).unwrap();
+        assert_eq!(info.filetype, "Python");
+        let info = count_bytes(b"#!/usr/bin/env bash\necho hi\n", Source::Path(Path::new("run"))).unwrap();
+        assert_eq!(info.filetype, "Shell");
+    }
 
-fn is_end_block_comments(line: &str) -> bool {
-    BLOCK_COMMENT_SUFFIXES.keys().any(|&suffix| line.ends_with(suffix))
+    #[test]
+    fn counts_a_reader_like_bytes() {
+        let content = b"#!/bin/sh\n# greet\necho hi\n\n";
+        for _ in 0..2 {
+            let info = count_reader(&content[..], Source::Path(Path::new("greet"))).unwrap();
+            let expected = count_bytes(content, Source::Path(Path::new("greet"))).unwrap();
+            assert_eq!(info.filetype, "Shell");
+            assert_eq!((info.steps, info.code, info.blanks, info.comments), (4, 1, 1, 2));
+            assert_eq!(info.bytes, expected.bytes);
+        }
+    }
+
+    #[test]
+    fn unknown_file_types_fall_back_to_the_extension() {
+        assert_eq!(ret_file_type(Path::new("notes.XYZ"), None), "XYZ");
+        assert_eq!(ret_file_type(Path::new("dir/LICENSE"), None), "LICENSE");
+        let rust = language::from_name("rust");
+        assert_eq!(ret_file_type(Path::new("main.rs"), rust), "Rust");
+    }
+
+    #[test]
+    fn unknown_language_name_is_an_error() {
+        let err = count_reader(&b"x"[..], Source::Language("no such language")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn embedded_languages_are_children() {
+        let info = html_with_script();
+        assert_eq!((info.steps, info.code), (3, 3));
+        assert_eq!(info.children.len(), 1);
+        let script = &info.children[0];
+        assert_eq!(script.filetype, "JavaScript");
+        assert_eq!((script.files, script.steps, script.mixed), (1, 1, 1));
+        assert_eq!(info.total().steps, 4);
+    }
+
+    #[test]
+    fn notebook_lines_belong_to_cells() {
+        let notebook = r##"{"cells": [{"cell_type": "code", "source": ["import os\n", "# list\n", "os.listdir()"]}]}"##;
+        let info = count_bytes(notebook.as_bytes(), Source::Path(Path::new("a.ipynb"))).unwrap();
+        assert_eq!((info.steps, info.bytes), (0, 0));
+        assert_eq!(info.children.len(), 1);
+        let cell = &info.children[0];
+        assert_eq!(cell.filetype, "Python");
+        assert_eq!((cell.steps, cell.code, cell.comments), (3, 2, 1));
+        assert_eq!(info.total().bytes, cell.bytes);
+    }
+
+    #[test]
+    fn unclosed_block_comments_are_warnings() {
+        let info = count_bytes(b"int x;\n/* open\n", Source::Language("c")).unwrap();
+        assert_eq!(info.warnings, ["<input>:2: block comment is never closed"]);
+        let content = "<p>x</p>\n<script>\nlet a = 1;\n/* open\n</script>\n";
+        let info = count_bytes(content.as_bytes(), Source::Path(Path::new("page.html"))).unwrap();
+        assert_eq!(info.warnings, ["page.html:4: block comment is never closed"]);
+    }
+
+    #[test]
+    fn invalid_notebook_is_counted_as_text_with_a_warning() {
+        let info = count_bytes(b"{\"cells\": [\n", Source::Path(Path::new("a.ipynb"))).unwrap();
+        assert_eq!(info.steps, 1);
+        assert_eq!(info.warnings.len(), 1);
+        assert!(info.warnings[0].starts_with("a.ipynb: not a valid notebook"));
+    }
+
+    #[test]
+    fn same_counts_with_any_number_of_threads() {
+        let dir = std::env::temp_dir().join(format!("steps-rust-count-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut files = Vec::new();
+        for index in 0..20 {
+            let path = dir.join(format!("file{}.rs", index));
+            fs::write(&path, "// c\nfn f() {}\n\n".repeat(index + 1)).unwrap();
+            files.push(path.to_string_lossy().to_string());
+        }
+        files.push(dir.join("missing.rs").to_string_lossy().to_string());
+
+        let results: Vec<CntResult> = [1, 4, 64]
+            .into_iter()
+            .map(|threads| {
+                let options = CountOptions {
+                    keep_files: true,
+                    threads: Some(threads),
+                };
+                count(files.clone(), String::new(), &options)
+            })
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        for result in &results {
+            assert_eq!(result.warnings.len(), 1);
+            assert!(result.warnings[0].contains("missing.rs"));
+            assert_eq!(result.all_files, 20);
+            assert_eq!(result.all_steps, 3 * 210);
+            assert_eq!(result.files.len(), 20);
+            assert_eq!(result.to_json(), results[0].to_json());
+        }
+    }
+
+    #[test]
+    fn empty_file_list() {
+        let result = count(Vec::new(), String::new(), &CountOptions::default());
+        assert_eq!(result.all_files, 0);
+        assert!(result.info.is_empty());
+    }
+
+    #[test]
+    fn merge_counts_files_per_embedded_language() {
+        let mut total = FileInfo::default();
+        for _ in 0..3 {
+            total.merge(&html_with_script());
+        }
+        assert_eq!(total.files, 3);
+        assert_eq!(total.children[0].files, 3);
+        assert_eq!(total.children[0].steps, 3);
+    }
+
+    #[test]
+    fn merge_results_of_separate_runs() {
+        let dir = std::env::temp_dir().join(format!("steps-rust-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let first = vec![write("a.rs", "fn a() {}\n"), write("b.py", "# b\n")];
+        let second = vec![write("c.rs", "// c\nfn c() {}\n")];
+        let options = CountOptions {
+            keep_files: true,
+            threads: None,
+        };
+        let mut result = count(first.clone(), "first".to_string(), &options);
+        result.merge(&count(second.clone(), "second".to_string(), &options));
+
+        assert_eq!(result.input_path, "first second");
+        assert_eq!((result.all_files, result.all_steps, result.all_code), (3, 4, 2));
+        let rust = result.info.iter().find(|info| info.filetype == "Rust").unwrap();
+        assert_eq!((rust.files, rust.steps, rust.comments), (2, 3, 1));
+        assert_eq!(result.info.len(), 2);
+        let paths: Vec<&str> = result.files.iter().map(|record| record.path.as_str()).collect();
+        assert_eq!(paths, [first[0].as_str(), first[1].as_str(), second[0].as_str()]);
+
+        // Records of one run alone would be an incomplete list.
+        let mut result = count(first, String::new(), &options);
+        result.merge(&count(second, String::new(), &CountOptions::default()));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result.all_files, 3);
+        assert!(result.files.is_empty());
+    }
+
+    #[test]
+    fn transcoded_bytes_are_those_of_the_decoded_text() {
+        let info = count_bytes(b"\xff\xfea\0=\x001\0\n\0", Source::Language("python")).unwrap();
+        assert_eq!(info.encoding.as_deref(), Some("UTF-16LE (BOM)"));
+        assert_eq!((info.steps, info.code, info.bytes), (1, 1, 4));
+    }
+
+    #[test]
+    fn binary_content_is_an_error() {
+        let err = count_bytes(b"\x7fELF\x02\x01\x01\0\0\0", Source::Path(Path::new("app.bin"))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn binary_files_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("steps-rust-binary-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("main.rs");
+        let image = dir.join("logo.png");
+        fs::write(&text, "fn main() {}\n").unwrap();
+        fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0").unwrap();
+        let files = vec![text.to_string_lossy().to_string(), image.to_string_lossy().to_string()];
+        let result = count(files, String::new(), &CountOptions::default());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.all_files, 1);
+        assert_eq!(result.info.len(), 1);
+        assert_eq!(result.info[0].filetype, "Rust");
+    }
 }
@@ -0,0 +1,250 @@
+use std::io;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::{CntResult, FileInfo, FileRecord};
+
+/// Version of the serialized report layout. Bumped whenever a field is
+/// renamed or removed; adding fields keeps the version.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// The document written by the JSON, YAML and TOML output: the schema
+/// version, the input paths, one entry per file type (sorted by name), the
+/// per-file records if kept, and the totals.
+#[derive(Serialize)]
+struct Document<'a> {
+    schema_version: u64,
+    input_path: &'a str,
+    languages: Vec<&'a FileInfo>,
+    files: &'a [FileRecord],
+    totals: Totals,
+}
+
+/// A [`Document`] read back.
+#[derive(Deserialize)]
+pub struct StoredDocument {
+    schema_version: u64,
+    input_path: String,
+    languages: Vec<FileInfo>,
+    files: Vec<FileRecord>,
+    totals: Totals,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Totals {
+    files: usize,
+    steps: usize,
+    code: usize,
+    blanks: usize,
+    comments: usize,
+    doc_comments: usize,
+    mixed: usize,
+    bytes: usize,
+}
+
+impl From<FileInfo> for Totals {
+    fn from(info: FileInfo) -> Self {
+        Totals {
+            files: info.files,
+            steps: info.steps,
+            code: info.code,
+            blanks: info.blanks,
+            comments: info.comments,
+            doc_comments: info.doc_comments,
+            mixed: info.mixed,
+            bytes: info.bytes,
+        }
+    }
+}
+
+/// Only the version, read first so that a newer layout is reported as such
+/// rather than as a missing field.
+#[derive(Deserialize)]
+struct Version {
+    schema_version: u64,
+}
+
+impl Serialize for CntResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut languages: Vec<&FileInfo> = self.info.iter().collect();
+        languages.sort_by(|a, b| a.filetype.cmp(&b.filetype));
+        Document {
+            schema_version: SCHEMA_VERSION,
+            input_path: &self.input_path,
+            languages,
+            files: &self.files,
+            totals: Totals::from(self.totals()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TryFrom<StoredDocument> for CntResult {
+    type Error = String;
+
+    fn try_from(document: StoredDocument) -> Result<Self, Self::Error> {
+        check_version(document.schema_version)?;
+        let totals = document.totals;
+        Ok(CntResult {
+            info: document.languages,
+            input_path: document.input_path,
+            all_steps: totals.steps,
+            all_code: totals.code,
+            all_blanks: totals.blanks,
+            all_comments: totals.comments,
+            all_doc_comments: totals.doc_comments,
+            all_mixed: totals.mixed,
+            all_files: totals.files,
+            all_bytes: totals.bytes,
+            files: document.files,
+            warnings: Vec::new(),
+        })
+    }
+}
+
+fn check_version(version: u64) -> Result<(), String> {
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(format!("unsupported schema version {}", version));
+    }
+    Ok(())
+}
+
+/// Writes embedded languages sorted by name, so that output does not depend
+/// on the order files were counted in.
+pub fn sorted<S: Serializer>(children: &[FileInfo], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut children: Vec<&FileInfo> = children.iter().collect();
+    children.sort_by(|a, b| a.filetype.cmp(&b.filetype));
+    serializer.collect_seq(children)
+}
+
+/// Reads back a report written by [`CntResult::to_json`]. Reports of a newer
+/// schema version are rejected.
+pub fn from_json(input: &str) -> io::Result<CntResult> {
+    let version: Version = serde_json::from_str(input).map_err(invalid)?;
+    check_version(version.schema_version).map_err(invalid)?;
+    serde_json::from_str(input).map_err(invalid)
+}
+
+fn invalid(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> CntResult {
+        let child = FileInfo {
+            filetype: "JavaScript".to_string(),
+            steps: 3,
+            code: 2,
+            comments: 1,
+            files: 1,
+            bytes: 40,
+            ..Default::default()
+        };
+        let html = FileInfo {
+            filetype: "HTML".to_string(),
+            steps: 10,
+            code: 8,
+            blanks: 2,
+            files: 1,
+            bytes: 120,
+            children: vec![child],
+            ..Default::default()
+        };
+        let rust = FileInfo {
+            filetype: "Rust".to_string(),
+            steps: 5,
+            code: 3,
+            comments: 2,
+            doc_comments: 1,
+            mixed: 1,
+            files: 1,
+            bytes: 80,
+            ..Default::default()
+        };
+        let mut result = CntResult {
+            input_path: "src \"quoted\"".to_string(),
+            files: vec![
+                FileRecord {
+                    path: "src/index.html".to_string(),
+                    info: FileInfo {
+                        encoding: Some("Shift_JIS".to_string()),
+                        ..html.clone()
+                    },
+                },
+                FileRecord {
+                    path: "src/main.rs".to_string(),
+                    info: FileInfo {
+                        encoding: Some("UTF-8".to_string()),
+                        ..rust.clone()
+                    },
+                },
+            ],
+            info: vec![rust, html],
+            ..Default::default()
+        };
+        result.assign_alls();
+        result
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let result = sample();
+        let json = result.to_json();
+        let loaded = CntResult::from_json(&json).unwrap();
+        assert_eq!(loaded.to_json(), json);
+        assert_eq!(loaded.input_path, result.input_path);
+        assert_eq!(loaded.all_steps, 18);
+        assert_eq!(loaded.all_files, 2);
+        assert_eq!(loaded.files[0].info.encoding.as_deref(), Some("Shift_JIS"));
+        assert_eq!(loaded.files[0].info.children[0].filetype, "JavaScript");
+    }
+
+    #[test]
+    fn languages_are_sorted_by_name() {
+        let json = sample().to_json();
+        assert!(json.find("\"HTML\"").unwrap() < json.find("\"Rust\"").unwrap());
+    }
+
+    #[test]
+    fn yaml_and_toml_hold_the_same_document() {
+        let result = sample();
+        let json = result.to_json();
+        let from_yaml: CntResult = serde_yaml::from_str(&result.to_yaml()).unwrap();
+        assert_eq!(from_yaml.to_json(), json);
+        let from_toml: CntResult = toml::from_str(&result.to_toml()).unwrap();
+        assert_eq!(from_toml.to_json(), json);
+    }
+
+    #[test]
+    fn rejects_newer_schema_version() {
+        let json = sample().to_json().replace("\"schema_version\": 1", "\"schema_version\": 2");
+        let err = CntResult::from_json(&json).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "unsupported schema version 2");
+
+        let err = CntResult::from_json(r#"{"schema_version": 99, "renamed": []}"#).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported schema version 99");
+    }
+
+    #[test]
+    fn rejects_invalid_documents() {
+        assert!(CntResult::from_json("{").is_err());
+        assert!(CntResult::from_json(r#"{"schema_version": 0}"#).is_err());
+        assert!(CntResult::from_json(r#"{"schema_version": 1}"#).is_err());
+        let json = sample().to_json().replace("\"steps\": 5", "\"steps\": -5");
+        assert!(CntResult::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn loads_records_without_encoding() {
+        let mut result = sample();
+        for record in &mut result.files {
+            record.info.encoding = None;
+        }
+        let loaded = CntResult::from_json(&result.to_json()).unwrap();
+        assert_eq!(loaded.files[1].info.encoding, None);
+    }
+}
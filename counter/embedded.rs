@@ -0,0 +1,301 @@
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use super::language::{self, Embedding, Language};
+
+/// Splits the lines of a file that embeds other languages into regions,
+/// line by line. Lines outside any region, including the lines holding the
+/// tags or fences that delimit a region, belong to the parent file.
+pub struct RegionSplitter {
+    embedding: Embedding,
+    /// Language of the region the splitter is in, if any.
+    region: Option<&'static Language>,
+    /// Marker closing the current region: `</script` / `</style` for HTML,
+    /// the opening fence for Markdown. Also set for fenced blocks in an
+    /// unknown language, which stay with the parent.
+    close: Option<Cow<'static, str>>,
+}
+
+/// An HTML element holding another language, with the start of its opening
+/// and closing tags.
+struct Element {
+    name: &'static str,
+    open: &'static str,
+    close: &'static str,
+}
+
+const ELEMENTS: [Element; 2] = [
+    Element {
+        name: "script",
+        open: "<script",
+        close: "</script",
+    },
+    Element {
+        name: "style",
+        open: "<style",
+        close: "</style",
+    },
+];
+
+impl RegionSplitter {
+    pub fn new(embedding: Embedding) -> Self {
+        RegionSplitter {
+            embedding,
+            region: None,
+            close: None,
+        }
+    }
+
+    /// Returns the embedded language `line` is written in, or `None` if it
+    /// belongs to the parent file.
+    pub fn route(&mut self, line: &str) -> Option<&'static Language> {
+        match self.embedding {
+            Embedding::Html => self.route_html(line),
+            Embedding::Markdown => self.route_markdown(line),
+            Embedding::Notebook => None,
+        }
+    }
+
+    fn route_html(&mut self, line: &str) -> Option<&'static Language> {
+        if let Some(close) = &self.close {
+            if find_ignore_case(line, close).is_some() {
+                self.close = None;
+                self.region = None;
+            }
+            return self.region;
+        }
+
+        for element in &ELEMENTS {
+            let Some(attributes) = opening_tag(line, element.open) else {
+                continue;
+            };
+            // `<script src="..."></script>` and one-line elements stay with
+            // the parent.
+            if find_ignore_case(line, element.close).is_some() {
+                return None;
+            }
+            self.region = element_language(element.name, attributes);
+            if self.region.is_some() {
+                self.close = Some(Cow::Borrowed(element.close));
+            }
+            return None;
+        }
+        None
+    }
+
+    fn route_markdown(&mut self, line: &str) -> Option<&'static Language> {
+        let trimmed = line.trim();
+        if let Some(fence) = &self.close {
+            let is_close = trimmed.len() >= fence.len()
+                && trimmed.chars().all(|c| fence.starts_with(c));
+            if is_close {
+                self.close = None;
+                self.region = None;
+                return None;
+            }
+            return self.region;
+        }
+
+        let marker = match trimmed.chars().next() {
+            Some(c @ ('`' | '~')) => c,
+            _ => return None,
+        };
+        let fence_len = trimmed.chars().take_while(|&c| c == marker).count();
+        if fence_len < 3 {
+            return None;
+        }
+        let info = trimmed[fence_len..].trim_start_matches(['{', '.', ' ']);
+        self.region = info
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '}')
+            .next()
+            .filter(|name| !name.is_empty())
+            .and_then(language::from_name);
+        self.close = Some(Cow::Owned(trimmed[..fence_len].to_string()));
+        None
+    }
+}
+
+/// If `line` opens an element with `open` (`<script`, `<style`) on this
+/// line, returns the attribute text of the tag.
+fn opening_tag<'a>(line: &'a str, open: &str) -> Option<&'a str> {
+    let start = find_ignore_case(line, open)? + open.len();
+    if !line[start..].starts_with(|c: char| c == '>' || c.is_whitespace()) {
+        return None;
+    }
+    let end = start + line[start..].find('>')?;
+    Some(&line[start..end])
+}
+
+/// Byte offset of the first occurrence of the ASCII `needle` in `haystack`,
+/// ignoring ASCII case.
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// The language of a `<script>` or `<style>` element, from its `lang` or
+/// `type` attribute. Scripts of an unknown type, such as templates, are
+/// left to the parent.
+fn element_language(tag: &str, attributes: &str) -> Option<&'static Language> {
+    if let Some(lang) = attribute(attributes, "lang") {
+        return language::from_name(&lang);
+    }
+    let kind = attribute(attributes, "type").unwrap_or_default().to_ascii_lowercase();
+    let name = match (tag, kind.as_str()) {
+        ("style", "" | "text/css") => "CSS",
+        ("script", "" | "module" | "text/javascript" | "application/javascript") => "JavaScript",
+        ("script", "text/typescript" | "application/typescript") => "TypeScript",
+        ("script", "application/json" | "application/ld+json" | "importmap") => "JSON",
+        _ => return None,
+    };
+    language::from_name(name)
+}
+
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let lower = attributes.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(pos) = lower[search..].find(name) {
+        let start = search + pos;
+        search = start + name.len();
+        let preceded = lower[..start].ends_with(|c: char| c.is_whitespace());
+        let rest = lower[search..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value = attributes[attributes.len() - rest.len() + 1..].trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            _ => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or(""),
+        };
+        return Some(value.to_string());
+    }
+    None
+}
+
+/// A cell of a Jupyter notebook: its language and source lines.
+pub struct Cell {
+    pub language: &'static Language,
+    pub lines: Vec<String>,
+}
+
+/// Extracts the code and Markdown cells of a notebook. Code cells are in the
+/// kernel's language, Python if the notebook does not say.
+pub fn notebook_cells(content: &str) -> serde_json::Result<Vec<Cell>> {
+    let notebook: Value = serde_json::from_str(content)?;
+    let metadata = notebook.get("metadata");
+    let kernel_language = metadata
+        .and_then(|m| m.get("language_info").and_then(|info| info.get("name")))
+        .or_else(|| metadata.and_then(|m| m.get("kernelspec").and_then(|spec| spec.get("language"))))
+        .and_then(Value::as_str)
+        .and_then(language::from_name)
+        .or_else(|| language::from_name("Python"));
+    let markdown = language::from_name("Markdown");
+
+    let mut cells = Vec::new();
+    for cell in notebook.get("cells").and_then(Value::as_array).into_iter().flatten() {
+        let language = match cell.get("cell_type").and_then(Value::as_str) {
+            Some("code") => kernel_language,
+            Some("markdown") => markdown,
+            _ => None,
+        };
+        let Some(language) = language else {
+            continue;
+        };
+
+        let source = match cell.get("source") {
+            Some(Value::String(source)) => source.clone(),
+            Some(Value::Array(parts)) => parts.iter().filter_map(Value::as_str).collect(),
+            _ => String::new(),
+        };
+        cells.push(Cell {
+            language,
+            lines: source.lines().map(str::to_string).collect(),
+        });
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(cells: &[Cell]) -> Vec<&str> {
+        cells.iter().map(|cell| cell.language.name).collect()
+    }
+
+    #[test]
+    fn notebook_cells_in_kernel_language() {
+        let notebook = r##"{
+            "metadata": {"kernelspec": {"language": "julia"}},
+            "cells": [
+                {"cell_type": "markdown", "source": "# Title\nText"},
+                {"cell_type": "code", "source": ["x = 1\n", "y = 2\n"]},
+                {"cell_type": "raw", "source": ["skipped"]}
+            ]
+        }"##;
+        let cells = notebook_cells(notebook).unwrap();
+        assert_eq!(names(&cells), ["Markdown", "Julia"]);
+        assert_eq!(cells[0].lines, ["# Title", "Text"]);
+        assert_eq!(cells[1].lines, ["x = 1", "y = 2"]);
+    }
+
+    #[test]
+    fn notebook_defaults_to_python() {
+        let cells = notebook_cells(r#"{"cells": [{"cell_type": "code", "source": "pass"}]}"#).unwrap();
+        assert_eq!(names(&cells), ["Python"]);
+    }
+
+    #[test]
+    fn deeply_nested_notebook_is_an_error() {
+        let notebook = format!("{{\"cells\": {}{}}}", "[".repeat(200_000), "]".repeat(200_000));
+        assert!(notebook_cells(&notebook).is_err());
+    }
+
+    #[test]
+    fn html_script_and_style_regions() {
+        let mut splitter = RegionSplitter::new(Embedding::Html);
+        let lines = [
+            "<p>text</p>",
+            "<script type=\"module\">",
+            "let x = 1;",
+            "</script>",
+            "<script src=\"a.js\"></script>",
+            "<style>",
+            "p { color: red; }",
+            "</style>",
+        ];
+        let routed: Vec<Option<&str>> = lines
+            .iter()
+            .map(|line| splitter.route(line).map(|language| language.name))
+            .collect();
+        assert_eq!(
+            routed,
+            [None, None, Some("JavaScript"), None, None, None, Some("CSS"), None]
+        );
+    }
+
+    #[test]
+    fn html_tags_ignore_case() {
+        let mut splitter = RegionSplitter::new(Embedding::Html);
+        let lines = ["<SCRIPT Type=\"Text/JavaScript\">", "var x;", "</Script>", "<Style>a {}</STYLE>"];
+        let routed: Vec<Option<&str>> = lines
+            .iter()
+            .map(|line| splitter.route(line).map(|language| language.name))
+            .collect();
+        assert_eq!(routed, [None, Some("JavaScript"), None, None]);
+    }
+
+    #[test]
+    fn markdown_fences() {
+        let mut splitter = RegionSplitter::new(Embedding::Markdown);
+        let lines = ["text", "````rust", "```", "fn f() {}", "````", "~~~ unknown", "x", "~~~"];
+        let routed: Vec<Option<&str>> = lines
+            .iter()
+            .map(|line| splitter.route(line).map(|language| language.name))
+            .collect();
+        assert_eq!(routed, [None, None, Some("Rust"), Some("Rust"), None, None, None, None]);
+    }
+}
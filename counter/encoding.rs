@@ -0,0 +1,204 @@
+use encoding_rs::{Encoding, EUC_JP, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use memchr::memchr;
+
+/// Guesses the encoding of a file from its first bytes: a byte order mark,
+/// the zero bytes of UTF-16 without one, (mostly) valid UTF-8, then
+/// Shift_JIS or EUC-JP if the bytes decode cleanly as either, and
+/// windows-1252 (Latin-1) as the last resort, which decodes anything.
+/// Returns `None` for binary content, which is not text in any encoding.
+pub fn detect(head: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(head) {
+        return Some(encoding);
+    }
+    if let Some(encoding) = utf16_without_bom(head) {
+        return Some(encoding);
+    }
+    if is_binary(head) {
+        return None;
+    }
+    Some(detect_text(head))
+}
+
+fn detect_text(head: &[u8]) -> &'static Encoding {
+    // The head may end in the middle of a character or line, so only
+    // complete lines are judged.
+    let head = match head.iter().rposition(|&b| b == b'\n') {
+        Some(pos) => &head[..=pos],
+        None => head,
+    };
+    match std::str::from_utf8(head) {
+        Ok(_) => return UTF_8,
+        Err(err) if err.error_len().is_none() => return UTF_8,
+        Err(_) => {}
+    }
+    if mostly_utf8(head) {
+        return UTF_8;
+    }
+
+    let japanese = [SHIFT_JIS, EUC_JP]
+        .into_iter()
+        .filter_map(|encoding| {
+            let text = encoding.decode_without_bom_handling_and_without_replacement(head)?;
+            Some((japanese_score(&text), encoding))
+        })
+        .max_by_key(|&(score, _)| score);
+    match japanese {
+        Some((score, encoding)) if score > 0 => encoding,
+        _ => WINDOWS_1252,
+    }
+}
+
+/// Text has no zero bytes (outside UTF-16, checked before) and few control
+/// characters other than tabs, line breaks, form feeds and escapes, which
+/// executables, images and archives are full of.
+fn is_binary(head: &[u8]) -> bool {
+    if memchr(0, head).is_some() {
+        return true;
+    }
+    let control = head
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || b == 0x7f)
+        .count();
+    control * 10 > head.len()
+}
+
+/// UTF-8 text with a few stray bytes, e.g. a pasted Latin-1 name, is still
+/// read as UTF-8; the stray bytes are replaced.
+fn mostly_utf8(head: &[u8]) -> bool {
+    let mut multibyte = 0;
+    let mut invalid = 0;
+    for chunk in head.utf8_chunks() {
+        multibyte += chunk.valid().chars().filter(|c| !c.is_ascii()).count();
+        if !chunk.invalid().is_empty() {
+            invalid += 1;
+        }
+    }
+    multibyte > invalid
+}
+
+/// ASCII text in UTF-16 has a zero byte in every other position: the high
+/// byte, which comes second in little-endian order. So do sound samples and
+/// tables of small numbers, which decode to mostly control characters.
+fn utf16_without_bom(head: &[u8]) -> Option<&'static Encoding> {
+    let units = head.len() / 2;
+    if units < 2 {
+        return None;
+    }
+    let zeros_at = |offset: usize| head.iter().skip(offset).step_by(2).take(units).filter(|&&b| b == 0).count();
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    let encoding = if odd * 10 >= units * 3 && even * 10 < units {
+        UTF_16LE
+    } else if even * 10 >= units * 3 && odd * 10 < units {
+        UTF_16BE
+    } else {
+        return None;
+    };
+
+    let (text, _) = encoding.decode_without_bom_handling(&head[..units * 2]);
+    let chars = text.chars().count();
+    let control = text
+        .chars()
+        .filter(|&c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+        .count();
+    (control * 10 <= chars).then_some(encoding)
+}
+
+/// How much `text` looks like Japanese: kana and kanji count for it,
+/// half-width katakana (which random high bytes often decode to in
+/// Shift_JIS) against it.
+fn japanese_score(text: &str) -> i64 {
+    text.chars()
+        .map(|c| match c {
+            '\u{3040}'..='\u{30ff}' | '\u{4e00}'..='\u{9fff}' | '\u{3000}'..='\u{303f}' | '\u{ff01}'..='\u{ff5e}' => 2,
+            '\u{ff61}'..='\u{ff9f}' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAPANESE: &str = "// 日本語のコメントです。\nlet x = \"こんにちは、世界\";\n";
+
+    fn encoded(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+        encoding.encode(text).0.into_owned()
+    }
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn byte_order_marks() {
+        assert_eq!(detect(b"\xEF\xBB\xBFfn main() {}\n"), Some(UTF_8));
+        assert_eq!(detect(b"\xFF\xFEa\0b\0"), Some(UTF_16LE));
+        assert_eq!(detect(b"\xFE\xFF\0a\0b"), Some(UTF_16BE));
+    }
+
+    #[test]
+    fn utf16_without_bom() {
+        let text = "int main() { return 0; }\n";
+        assert_eq!(detect(&utf16(text, false)), Some(UTF_16LE));
+        assert_eq!(detect(&utf16(text, true)), Some(UTF_16BE));
+    }
+
+    #[test]
+    fn utf8() {
+        assert_eq!(detect(b"plain ascii\n"), Some(UTF_8));
+        assert_eq!(detect(JAPANESE.as_bytes()), Some(UTF_8));
+        assert_eq!(detect(b""), Some(UTF_8));
+    }
+
+    #[test]
+    fn utf8_cut_off_mid_character() {
+        let bytes = JAPANESE.as_bytes();
+        assert_eq!(detect(&bytes[..bytes.len() - 5]), Some(UTF_8));
+    }
+
+    #[test]
+    fn mostly_utf8_with_a_stray_byte() {
+        let mut bytes = JAPANESE.as_bytes().to_vec();
+        bytes.extend_from_slice(b"// caf\xE9\n");
+        assert_eq!(detect(&bytes), Some(UTF_8));
+    }
+
+    #[test]
+    fn shift_jis_and_euc_jp() {
+        assert_eq!(detect(&encoded(SHIFT_JIS, JAPANESE)), Some(SHIFT_JIS));
+        assert_eq!(detect(&encoded(EUC_JP, JAPANESE)), Some(EUC_JP));
+    }
+
+    #[test]
+    fn latin1_falls_back_to_windows_1252() {
+        let bytes = encoded(WINDOWS_1252, "# Café, naïve, Jürgen\nprint('ok')\n");
+        assert_eq!(detect(&bytes), Some(WINDOWS_1252));
+    }
+
+    #[test]
+    fn binary_content() {
+        assert_eq!(detect(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x03\0>\0\x01\0\0\0"), None);
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0"), None);
+        assert_eq!(detect(b"text\0with a zero byte\n"), None);
+        assert_eq!(detect(b"\x01\x02\x03\x04\x05\x06abc\x07\x08"), None);
+    }
+
+    #[test]
+    fn sound_samples_are_not_utf16() {
+        let mut wav = b"RIFF\xa4\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0\x44\xac\0\0\x88\x58\x01\0\x02\0\x10\0data\x80\0\0\0".to_vec();
+        wav.extend((0..64u16).flat_map(|i| (i * 37 % 250 + 1).to_le_bytes()));
+        assert_eq!(detect(&wav), None);
+
+        let table: Vec<u8> = (1..=255u16).flat_map(u16::to_le_bytes).collect();
+        assert_eq!(detect(&table), None);
+    }
+
+    #[test]
+    fn text_with_a_few_control_characters() {
+        assert_eq!(detect(b"\x1b[1mbold\x1b[0m\tand\x0cpage\r\n"), Some(UTF_8));
+        assert_eq!(detect(b"a line with one bell \x07 in it\n"), Some(UTF_8));
+    }
+}
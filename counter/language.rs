@@ -0,0 +1,1245 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct Language {
+    /// Canonical name reported as `FileInfo::filetype`.
+    pub name: &'static str,
+    /// File extensions without the leading dot, lowercase.
+    pub extensions: &'static [&'static str],
+    /// Well-known file names, matched case-insensitively.
+    pub filenames: &'static [&'static str],
+    /// Interpreter names recognised in `#!` lines, lowercase.
+    pub interpreters: &'static [&'static str],
+    /// Prefixes starting a comment that runs to the end of the line.
+    pub line_comments: &'static [&'static str],
+    /// Whether line comments only start a word, i.e. at the start of the
+    /// line or after whitespace, as `#` in shell where `${#arr[@]}` is code.
+    pub word_comments: bool,
+    /// Start and end delimiters of block comments.
+    pub block_comments: &'static [(&'static str, &'static str)],
+    /// Start and end delimiters of block comments that nest, e.g. Rust's
+    /// `/* outer /* inner */ still comment */`.
+    pub nested_comments: &'static [(&'static str, &'static str)],
+    /// Prefixes marking a line or block comment as documentation, e.g. `///`
+    /// or `/**`. A prefix followed by its own last character (`////`,
+    /// `/***`) is an ordinary comment.
+    pub doc_comments: &'static [&'static str],
+    /// Whether a string literal opening a module or following a `def` or
+    /// `class` header is a docstring, as in Python.
+    pub docstrings: bool,
+    /// How the language embeds code written in other languages, if it does.
+    pub embedded: Option<Embedding>,
+    /// String literal delimiters. Comment markers inside them are ignored.
+    /// Longer delimiters must come before their prefixes.
+    pub quotes: &'static [Quote],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Embedding {
+    /// `<script>` and `<style>` elements.
+    Html,
+    /// Fenced code blocks.
+    Markdown,
+    /// Code and Markdown cells of a Jupyter notebook.
+    Notebook,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BlockComment {
+    pub start: &'static str,
+    pub end: &'static str,
+    pub nested: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub start: &'static str,
+    pub end: &'static str,
+    /// Character escaping the next character inside the literal, if any.
+    pub escape: Option<char>,
+    /// Whether the literal may continue past the end of a line.
+    pub multiline: bool,
+    /// Whether the literal holds a single character, as in Rust where `'`
+    /// also starts a lifetime. It only opens a literal if the closing
+    /// delimiter follows one character or escape later.
+    pub single_char: bool,
+}
+
+impl Quote {
+    /// A single-line literal with backslash escapes.
+    pub const fn escaped(start: &'static str, end: &'static str) -> Quote {
+        Quote {
+            start,
+            end,
+            escape: Some('\\'),
+            multiline: false,
+            single_char: false,
+        }
+    }
+
+    /// A single-line literal without escapes.
+    pub const fn raw(start: &'static str, end: &'static str) -> Quote {
+        Quote {
+            start,
+            end,
+            escape: None,
+            multiline: false,
+            single_char: false,
+        }
+    }
+
+    pub const fn multiline(mut self) -> Quote {
+        self.multiline = true;
+        self
+    }
+
+    pub const fn escape(mut self, escape: char) -> Quote {
+        self.escape = Some(escape);
+        self
+    }
+
+    pub const fn single_char(mut self) -> Quote {
+        self.single_char = true;
+        self
+    }
+
+    /// Whether the literal starting at the beginning of `rest` is closed
+    /// after a single character or escape, e.g. `'x'`, `'\n'` or
+    /// `'\u{1F600}'`.
+    fn closes_after_one_char(&self, rest: &str) -> bool {
+        let mut chars = rest[self.start.len()..].chars();
+        let after = match chars.next() {
+            Some(c) if Some(c) == self.escape => {
+                let escaped = chars.as_str();
+                match escaped.strip_prefix("u{") {
+                    Some(code) => match code.find('}') {
+                        Some(close) => &code[close + 1..],
+                        None => return false,
+                    },
+                    None => {
+                        let mut chars = escaped.chars();
+                        if chars.next().is_none() {
+                            return false;
+                        }
+                        chars.as_str()
+                    }
+                }
+            }
+            Some(c) if !self.end.starts_with(c) => chars.as_str(),
+            _ => return false,
+        };
+        after.starts_with(self.end)
+    }
+}
+
+const BASE: Language = Language {
+    name: "",
+    extensions: &[],
+    filenames: &[],
+    interpreters: &[],
+    line_comments: &[],
+    word_comments: false,
+    block_comments: &[],
+    nested_comments: &[],
+    doc_comments: &[],
+    docstrings: false,
+    embedded: None,
+    quotes: &[],
+};
+
+const DOXYGEN: &[&str] = &["///", "//!", "/**", "/*!"];
+const JAVADOC: &[&str] = &["///", "/**"];
+
+const C_QUOTES: &[Quote] = &[
+    Quote::raw("R\"(", ")\"").multiline(),
+    Quote::escaped("\"", "\""),
+    Quote::escaped("'", "'"),
+];
+
+const JS_QUOTES: &[Quote] = &[
+    Quote::escaped("`", "`").multiline(),
+    Quote::escaped("\"", "\""),
+    Quote::escaped("'", "'"),
+];
+
+// Lifetimes make `'` unbalanced in Rust, so `'` only opens a char literal
+// that closes right after one character: `'a'` is a literal, `'a` in
+// `&'a str` is not.
+const RUST_QUOTES: &[Quote] = &[
+    Quote::raw("r###\"", "\"###").multiline(),
+    Quote::raw("r##\"", "\"##").multiline(),
+    Quote::raw("r#\"", "\"#").multiline(),
+    Quote::raw("r\"", "\"").multiline(),
+    Quote::raw("br##\"", "\"##").multiline(),
+    Quote::raw("br#\"", "\"#").multiline(),
+    Quote::raw("br\"", "\"").multiline(),
+    Quote::escaped("\"", "\"").multiline(),
+    Quote::escaped("'", "'").single_char(),
+];
+
+// Prefixed triple-quoted strings are listed so that `r"""` at the start of a
+// statement is still a docstring rather than code followed by a string.
+const PYTHON_QUOTES: &[Quote] = &[
+    Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("'''", "'''").multiline(),
+    Quote::escaped("r\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("r'''", "'''").multiline(),
+    Quote::escaped("R\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("R'''", "'''").multiline(),
+    Quote::escaped("u\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("u'''", "'''").multiline(),
+    Quote::escaped("U\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("U'''", "'''").multiline(),
+    Quote::escaped("b\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("b'''", "'''").multiline(),
+    Quote::escaped("f\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("f'''", "'''").multiline(),
+    Quote::escaped("rb\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("rb'''", "'''").multiline(),
+    Quote::escaped("br\"\"\"", "\"\"\"").multiline(),
+    Quote::escaped("br'''", "'''").multiline(),
+    Quote::escaped("\"", "\""),
+    Quote::escaped("'", "'"),
+];
+
+// Every entry ends in `..BASE` so new fields only need to be spelled out
+// where they matter.
+#[allow(clippy::needless_update)]
+pub static LANGUAGES: &[Language] = &[
+    Language {
+        name: "Ada",
+        extensions: &["ada", "adb", "ads"],
+        line_comments: &["--"],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "APL",
+        extensions: &["apl"],
+        line_comments: &["⍝"],
+        ..BASE
+    },
+    Language {
+        name: "AsciiDoc",
+        extensions: &["adoc", "asciidoc"],
+        line_comments: &["//"],
+        block_comments: &[("////", "////")],
+        ..BASE
+    },
+    Language {
+        name: "Assembly",
+        extensions: &["asm", "s"],
+        line_comments: &[";"],
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "AWK",
+        extensions: &["awk"],
+        interpreters: &["awk", "gawk", "mawk", "nawk"],
+        line_comments: &["#"],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "Batch",
+        extensions: &["bat", "cmd"],
+        line_comments: &["::", "rem ", "REM ", "Rem ", "@rem ", "@REM "],
+        ..BASE
+    },
+    Language {
+        name: "C",
+        extensions: &["c"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: DOXYGEN,
+        quotes: C_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "C Header",
+        extensions: &["h", "hh", "hpp", "hxx", "h++", "inl"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: DOXYGEN,
+        quotes: C_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "C++",
+        extensions: &["cpp", "cc", "cxx", "c++", "cp", "tcc"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: DOXYGEN,
+        quotes: C_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs", "csx"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[
+            Quote::raw("\"\"\"", "\"\"\"").multiline(),
+            Quote::raw("@\"", "\"").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Clojure",
+        extensions: &["clj", "cljs", "cljc", "edn"],
+        line_comments: &[";"],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "CMake",
+        extensions: &["cmake"],
+        filenames: &["cmakelists.txt"],
+        line_comments: &["#"],
+        block_comments: &[("#[[", "]]")],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "COBOL",
+        extensions: &["cob", "cbl"],
+        line_comments: &["*>"],
+        ..BASE
+    },
+    Language {
+        name: "CoffeeScript",
+        extensions: &["coffee"],
+        interpreters: &["coffee"],
+        line_comments: &["#"],
+        block_comments: &[("###", "###")],
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("'''", "'''").multiline(),
+            Quote::escaped("\"", "\"").multiline(),
+            Quote::escaped("'", "'").multiline(),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Crystal",
+        extensions: &["cr"],
+        interpreters: &["crystal"],
+        line_comments: &["#"],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "D",
+        extensions: &["d"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        nested_comments: &[("/+", "+/")],
+        doc_comments: &["///", "/**", "/++"],
+        quotes: &[
+            Quote::raw("r\"", "\"").multiline(),
+            Quote::raw("`", "`").multiline(),
+            Quote::escaped("\"", "\"").multiline(),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Dart",
+        extensions: &["dart"],
+        line_comments: &["//"],
+        nested_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("'''", "'''").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Dockerfile",
+        extensions: &["dockerfile"],
+        filenames: &["dockerfile", "containerfile"],
+        line_comments: &["#"],
+        ..BASE
+    },
+    Language {
+        name: "Elixir",
+        extensions: &["ex", "exs"],
+        interpreters: &["elixir"],
+        line_comments: &["#"],
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("'''", "'''").multiline(),
+            Quote::escaped("\"", "\"").multiline(),
+            Quote::escaped("'", "'").multiline(),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Elm",
+        extensions: &["elm"],
+        line_comments: &["--"],
+        nested_comments: &[("{-", "-}")],
+        doc_comments: &["{-|"],
+        quotes: &[Quote::escaped("\"\"\"", "\"\"\"").multiline(), Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "Emacs Lisp",
+        extensions: &["el"],
+        filenames: &[".emacs"],
+        line_comments: &[";"],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Erlang",
+        extensions: &["erl", "hrl"],
+        filenames: &["rebar.config"],
+        line_comments: &["%"],
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "F#",
+        extensions: &["fs", "fsi", "fsx"],
+        line_comments: &["//"],
+        nested_comments: &[("(*", "*)")],
+        doc_comments: &["///"],
+        quotes: &[
+            Quote::raw("\"\"\"", "\"\"\"").multiline(),
+            Quote::raw("@\"", "\"").multiline(),
+            Quote::escaped("\"", "\"").multiline(),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Fish",
+        extensions: &["fish"],
+        interpreters: &["fish"],
+        line_comments: &["#"],
+        word_comments: true,
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::escaped("'", "'").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Fortran",
+        extensions: &["f", "for", "f77", "f90", "f95", "f03", "f08"],
+        line_comments: &["!"],
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[
+            Quote::raw("`", "`").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "GraphQL",
+        extensions: &["graphql", "gql"],
+        line_comments: &["#"],
+        quotes: &[Quote::raw("\"\"\"", "\"\"\"").multiline(), Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "Groovy",
+        extensions: &["groovy", "gradle"],
+        filenames: &["jenkinsfile"],
+        interpreters: &["groovy"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("'''", "'''").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        line_comments: &["--"],
+        nested_comments: &[("{-", "-}")],
+        doc_comments: &["-- |", "-- ^", "{-|"],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "HCL",
+        extensions: &["hcl", "tf", "tfvars"],
+        line_comments: &["#", "//"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm", "xhtml"],
+        block_comments: &[("<!--", "-->")],
+        embedded: Some(Embedding::Html),
+        ..BASE
+    },
+    Language {
+        name: "Ignore List",
+        filenames: &[".gitignore", ".ignore", ".stepsignore", ".dockerignore"],
+        line_comments: &["#"],
+        ..BASE
+    },
+    Language {
+        name: "INI",
+        extensions: &["ini", "cfg"],
+        line_comments: &[";", "#"],
+        ..BASE
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs"],
+        interpreters: &["node", "nodejs"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: JS_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json"],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "JSP",
+        extensions: &["jsp"],
+        block_comments: &[("<%--", "--%>"), ("<!--", "-->")],
+        ..BASE
+    },
+    Language {
+        name: "JSX",
+        extensions: &["jsx"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: JS_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "Julia",
+        extensions: &["jl"],
+        interpreters: &["julia"],
+        line_comments: &["#"],
+        nested_comments: &[("#=", "=#")],
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("\"", "\"").multiline(),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Jupyter Notebook",
+        extensions: &["ipynb"],
+        embedded: Some(Embedding::Notebook),
+        ..BASE
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        line_comments: &["//"],
+        nested_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[
+            Quote::raw("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Less",
+        extensions: &["less"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Lisp",
+        extensions: &["lisp", "lsp", "cl"],
+        line_comments: &[";"],
+        nested_comments: &[("#|", "|#")],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        interpreters: &["lua", "luajit"],
+        line_comments: &["--"],
+        block_comments: &[("--[[", "]]")],
+        doc_comments: &["---"],
+        quotes: &[
+            Quote::raw("[[", "]]").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Makefile",
+        extensions: &["mk", "mak"],
+        filenames: &["makefile", "gnumakefile"],
+        interpreters: &["make"],
+        line_comments: &["#"],
+        word_comments: true,
+        ..BASE
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        block_comments: &[("<!--", "-->")],
+        embedded: Some(Embedding::Markdown),
+        ..BASE
+    },
+    Language {
+        name: "Nim",
+        extensions: &["nim"],
+        line_comments: &["#"],
+        nested_comments: &[("#[", "]#")],
+        quotes: &[
+            Quote::raw("\"\"\"", "\"\"\"").multiline(),
+            Quote::raw("r\"", "\""),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Nix",
+        extensions: &["nix"],
+        line_comments: &["#"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::raw("''", "''").multiline(), Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Objective-C",
+        extensions: &["m"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: DOXYGEN,
+        quotes: &[
+            Quote::escaped("@\"", "\""),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Objective-C++",
+        extensions: &["mm"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: DOXYGEN,
+        quotes: &[
+            Quote::escaped("@\"", "\""),
+            Quote::raw("R\"(", ")\"").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "OCaml",
+        extensions: &["ml", "mli"],
+        nested_comments: &[("(*", "*)")],
+        doc_comments: &["(**"],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Pascal",
+        extensions: &["pas", "dpr"],
+        line_comments: &["//"],
+        block_comments: &[("{", "}"), ("(*", "*)")],
+        quotes: &[Quote::raw("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Perl",
+        extensions: &["pl", "pm", "pod"],
+        interpreters: &["perl"],
+        line_comments: &["#"],
+        block_comments: &[("=pod", "=cut"), ("=head", "=cut"), ("=begin", "=cut"), ("=comment", "=cut")],
+        doc_comments: &["=pod", "=head"],
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::escaped("'", "'").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        interpreters: &["php"],
+        line_comments: &["//", "#"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::escaped("'", "'").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Plain Text",
+        extensions: &["txt"],
+        ..BASE
+    },
+    Language {
+        name: "PowerShell",
+        extensions: &["ps1", "psm1", "psd1"],
+        interpreters: &["pwsh", "powershell"],
+        line_comments: &["#"],
+        block_comments: &[("<#", "#>")],
+        quotes: &[
+            Quote::raw("@\"", "\"@").multiline(),
+            Quote::raw("@'", "'@").multiline(),
+            Quote::escaped("\"", "\"").escape('`').multiline(),
+            Quote::raw("'", "'").multiline(),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Protocol Buffers",
+        extensions: &["proto"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyw", "pyi"],
+        interpreters: &["python", "python2", "python3", "pypy", "pypy3"],
+        line_comments: &["#"],
+        docstrings: true,
+        quotes: PYTHON_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "R",
+        extensions: &["r"],
+        interpreters: &["rscript"],
+        line_comments: &["#"],
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::escaped("'", "'").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Racket",
+        extensions: &["rkt"],
+        interpreters: &["racket"],
+        line_comments: &[";"],
+        nested_comments: &[("#|", "|#")],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "reStructuredText",
+        extensions: &["rst"],
+        ..BASE
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb", "rake", "gemspec"],
+        filenames: &["rakefile", "gemfile"],
+        interpreters: &["ruby", "jruby"],
+        line_comments: &["#"],
+        block_comments: &[("=begin", "=end")],
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::escaped("'", "'").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        line_comments: &["//"],
+        nested_comments: &[("/*", "*/")],
+        doc_comments: DOXYGEN,
+        quotes: RUST_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "Sass",
+        extensions: &["sass", "scss"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Scala",
+        extensions: &["scala", "sc"],
+        interpreters: &["scala"],
+        line_comments: &["//"],
+        nested_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[
+            Quote::raw("\"\"\"", "\"\"\"").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::escaped("'", "'"),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "Scheme",
+        extensions: &["scm", "ss"],
+        interpreters: &["guile", "scheme"],
+        line_comments: &[";"],
+        nested_comments: &[("#|", "|#")],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "ksh"],
+        filenames: &[".bashrc", ".bash_profile", ".profile", ".zshrc"],
+        interpreters: &["sh", "bash", "zsh", "ksh", "dash", "ash"],
+        line_comments: &["#"],
+        word_comments: true,
+        quotes: &[Quote::escaped("\"", "\"").multiline(), Quote::raw("'", "'").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Solidity",
+        extensions: &["sol"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[Quote::escaped("\"", "\""), Quote::escaped("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        line_comments: &["--"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::raw("'", "'").multiline(), Quote::raw("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "Svelte",
+        extensions: &["svelte"],
+        block_comments: &[("<!--", "-->")],
+        embedded: Some(Embedding::Html),
+        ..BASE
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        line_comments: &["//"],
+        nested_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: &[Quote::escaped("\"\"\"", "\"\"\"").multiline(), Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "Tcl",
+        extensions: &["tcl"],
+        interpreters: &["tclsh", "wish"],
+        line_comments: &["#"],
+        quotes: &[Quote::escaped("\"", "\"").multiline()],
+        ..BASE
+    },
+    Language {
+        name: "TeX",
+        extensions: &["tex", "sty", "cls"],
+        line_comments: &["%"],
+        ..BASE
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        filenames: &["cargo.lock"],
+        line_comments: &["#"],
+        quotes: &[
+            Quote::escaped("\"\"\"", "\"\"\"").multiline(),
+            Quote::raw("'''", "'''").multiline(),
+            Quote::escaped("\"", "\""),
+            Quote::raw("'", "'").multiline(),
+        ],
+        ..BASE
+    },
+    Language {
+        name: "TSX",
+        extensions: &["tsx"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: JS_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "mts", "cts"],
+        interpreters: &["ts-node", "tsx"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        doc_comments: JAVADOC,
+        quotes: JS_QUOTES,
+        ..BASE
+    },
+    Language {
+        name: "Verilog",
+        extensions: &["v", "sv", "svh", "vh"],
+        line_comments: &["//"],
+        block_comments: &[("/*", "*/")],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "VHDL",
+        extensions: &["vhd", "vhdl"],
+        line_comments: &["--"],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "Vim Script",
+        extensions: &["vim"],
+        filenames: &[".vimrc", "vimrc", "_vimrc"],
+        line_comments: &["\""],
+        quotes: &[Quote::raw("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Visual Basic",
+        extensions: &["vb", "vbs", "bas"],
+        line_comments: &["'", "rem ", "REM ", "Rem "],
+        quotes: &[Quote::raw("\"", "\"")],
+        ..BASE
+    },
+    Language {
+        name: "Vue",
+        extensions: &["vue"],
+        block_comments: &[("<!--", "-->")],
+        embedded: Some(Embedding::Html),
+        ..BASE
+    },
+    Language {
+        name: "XML",
+        extensions: &["xml", "xsd", "xsl", "xslt", "svg", "plist"],
+        block_comments: &[("<!--", "-->")],
+        ..BASE
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yaml", "yml"],
+        line_comments: &["#"],
+        word_comments: true,
+        quotes: &[Quote::escaped("\"", "\""), Quote::raw("'", "'")],
+        ..BASE
+    },
+    Language {
+        name: "Zig",
+        extensions: &["zig"],
+        line_comments: &["//"],
+        doc_comments: &["///", "//!"],
+        quotes: &[Quote::escaped("\"", "\"")],
+        ..BASE
+    },
+];
+
+lazy_static::lazy_static! {
+    static ref BY_EXTENSION: HashMap<&'static str, &'static Language> = {
+        let mut m = HashMap::new();
+        for language in LANGUAGES {
+            for &ext in language.extensions {
+                m.insert(ext, language);
+            }
+        }
+        m
+    };
+
+    static ref BY_FILENAME: HashMap<&'static str, &'static Language> = {
+        let mut m = HashMap::new();
+        for language in LANGUAGES {
+            for &name in language.filenames {
+                m.insert(name, language);
+            }
+        }
+        m
+    };
+
+    static ref BY_INTERPRETER: HashMap<&'static str, &'static Language> = {
+        let mut m = HashMap::new();
+        for language in LANGUAGES {
+            for &name in language.interpreters {
+                m.insert(name, language);
+            }
+        }
+        m
+    };
+
+    static ref BY_NAME: HashMap<String, &'static Language> = {
+        let mut m = HashMap::new();
+        for language in LANGUAGES {
+            m.insert(language.name.to_lowercase(), language);
+        }
+        m
+    };
+}
+
+/// Editor mode names that match neither a language name, an interpreter nor
+/// an extension.
+const MODE_ALIASES: [(&str, &str); 4] = [
+    ("shell-script", "Shell"),
+    ("emacs-lisp", "Emacs Lisp"),
+    ("elisp", "Emacs Lisp"),
+    ("objc", "Objective-C"),
+];
+
+/// Number of leading lines searched for an editor modeline.
+const MODELINE_LINES: usize = 5;
+
+/// Detects the language of `path` from its file name, then its extension.
+/// Both are matched case-insensitively.
+pub fn from_path(path: &Path) -> Option<&'static Language> {
+    let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+    if let Some(language) = BY_FILENAME.get(file_name.as_str()) {
+        return Some(language);
+    }
+
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    BY_EXTENSION.get(ext.as_str()).copied()
+}
+
+/// Detects the language from the beginning of a file's content: a `#!` line
+/// on the first line, or a Vim or Emacs modeline in the first few lines.
+pub fn from_head(head: &str) -> Option<&'static Language> {
+    let mut lines = head.lines();
+    let first = lines.next()?;
+    if let Some(language) = from_shebang(first) {
+        return Some(language);
+    }
+    std::iter::once(first)
+        .chain(lines)
+        .take(MODELINE_LINES)
+        .find_map(from_modeline)
+}
+
+/// Parses `#!/usr/bin/python3`, `#!/usr/bin/env python3` and
+/// `#!/usr/bin/env -S python3 -u` style lines.
+fn from_shebang(line: &str) -> Option<&'static Language> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = basename(words.next()?);
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-') && !word.contains('=')).map(basename)?;
+    }
+    from_interpreter(interpreter)
+}
+
+fn from_interpreter(name: &str) -> Option<&'static Language> {
+    let name = name.to_lowercase();
+    if let Some(language) = BY_INTERPRETER.get(name.as_str()) {
+        return Some(language);
+    }
+    // python3.11, ruby2.7, ...
+    let unversioned = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    BY_INTERPRETER.get(unversioned).copied()
+}
+
+/// Parses Vim modelines (`vim: set ft=python:`, `vi: filetype=sh`) and Emacs
+/// modelines (`-*- mode: ruby -*-`, `-*- perl -*-`).
+fn from_modeline(line: &str) -> Option<&'static Language> {
+    if let Some(start) = line.find("-*-") {
+        let rest = &line[start + 3..];
+        let vars = &rest[..rest.find("-*-")?];
+        let mode = if vars.contains(':') {
+            vars.split(';').find_map(|var| {
+                let (key, value) = var.split_once(':')?;
+                key.trim().eq_ignore_ascii_case("mode").then(|| value.trim())
+            })?
+        } else {
+            vars.trim()
+        };
+        return from_name(mode);
+    }
+
+    let (_, settings) = ["vim:", "vi:", "ex:", "Vim:"]
+        .iter()
+        .find_map(|marker| line.split_once(marker))?;
+    settings
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|setting| {
+            let (key, value) = setting.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syntax").then_some(value)
+        })
+        .and_then(from_name)
+}
+
+/// Looks up a language by a loosely spelled name: its canonical name, an
+/// editor mode, an interpreter or an extension, e.g. `Rust`, `sh`, `python3`
+/// or `ts`.
+pub fn from_name(mode: &str) -> Option<&'static Language> {
+    let mode = mode.to_lowercase();
+    if let Some(&(_, name)) = MODE_ALIASES.iter().find(|(alias, _)| *alias == mode) {
+        return BY_NAME.get(&name.to_lowercase()).copied();
+    }
+    BY_NAME
+        .get(&mode)
+        .or_else(|| BY_INTERPRETER.get(mode.as_str()))
+        .or_else(|| BY_EXTENSION.get(mode.as_str()))
+        .copied()
+}
+
+fn basename(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl Language {
+    /// Whether a line comment starts at the beginning of `rest`, `rest` being
+    /// the remainder of a line from column `column` and `prev` the character
+    /// before it.
+    pub fn line_comment_at(&self, rest: &str, column: usize, prev: Option<char>) -> bool {
+        if self.word_comments && !prev.is_none_or(char::is_whitespace) {
+            return false;
+        }
+        self.line_comments
+            .iter()
+            .any(|prefix| rest.starts_with(prefix) && (column == 0 || !is_line_anchored(prefix)))
+    }
+
+    /// Whether the comment starting at the beginning of `rest` is a
+    /// documentation comment.
+    pub fn is_doc_comment(&self, rest: &str) -> bool {
+        self.doc_comments.iter().any(|prefix| {
+            rest.starts_with(prefix) && !rest[prefix.len()..].starts_with(prefix.chars().last().unwrap())
+        })
+    }
+
+    /// If a string literal starts at the beginning of `rest`, returns its
+    /// delimiters. `prev` is the character before `rest` on the line; a
+    /// delimiter starting with a letter, like Rust's `r"`, does not match
+    /// inside an identifier.
+    pub fn quote_at(&self, rest: &str, prev: Option<char>) -> Option<&'static Quote> {
+        let in_identifier = prev.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        self.quotes.iter().find(|quote| {
+            rest.starts_with(quote.start)
+                && !(in_identifier && quote.start.starts_with(|c: char| c.is_alphabetic()))
+                && (!quote.single_char || quote.closes_after_one_char(rest))
+        })
+    }
+
+    /// If a block comment starts at the beginning of `rest`, returns it.
+    pub fn block_comment_at(&self, rest: &str, column: usize) -> Option<BlockComment> {
+        let starts = |&&(start, _): &&(&str, &str)| {
+            rest.starts_with(start) && (column == 0 || !is_line_anchored(start))
+        };
+        if let Some(&(start, end)) = self.nested_comments.iter().find(starts) {
+            return Some(BlockComment { start, end, nested: true });
+        }
+        self.block_comments.iter().find(starts).map(|&(start, end)| BlockComment {
+            start,
+            end,
+            nested: false,
+        })
+    }
+}
+
+/// Word-like delimiters such as Batch's `rem` or Perl's `=pod` / `=cut` are
+/// only recognised at the start of a line.
+pub fn is_line_anchored(delimiter: &str) -> bool {
+    let mut chars = delimiter.chars();
+    match chars.next() {
+        Some('=') | Some('@') => chars.next().is_some_and(|c| c.is_ascii_alphabetic()),
+        Some(c) => c.is_ascii_alphabetic(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_language(path: &str) -> Option<&'static str> {
+        from_path(Path::new(path)).map(|language| language.name)
+    }
+
+    fn head_language(head: &str) -> Option<&'static str> {
+        from_head(head).map(|language| language.name)
+    }
+
+    #[test]
+    fn c_headers() {
+        for path in ["a.h", "b.hh", "include/c.hpp"] {
+            assert_eq!(path_language(path), Some("C Header"), "{}", path);
+        }
+    }
+
+    #[test]
+    fn well_known_file_names() {
+        for path in ["Makefile", "makefile", "src/GNUmakefile"] {
+            assert_eq!(path_language(path), Some("Makefile"), "{}", path);
+        }
+        assert_eq!(path_language("CMakeLists.txt"), Some("CMake"));
+        assert_eq!(path_language("docker/Dockerfile"), Some("Dockerfile"));
+    }
+
+    #[test]
+    fn extensions_ignore_case() {
+        assert_eq!(path_language("MAIN.RS"), Some("Rust"));
+        assert_eq!(path_language("Setup.Py"), Some("Python"));
+    }
+
+    #[test]
+    fn unknown_paths() {
+        assert_eq!(path_language("notes.xyz"), None);
+        assert_eq!(path_language("LICENSE"), None);
+    }
+
+    #[test]
+    fn shebangs() {
+        assert_eq!(head_language("#!/bin/sh\necho hi\n"), Some("Shell"));
+        assert_eq!(head_language("#!/usr/bin/env -S python3 -u\n"), Some("Python"));
+        assert_eq!(head_language("#!/usr/bin/env LANG=C perl -w\n"), Some("Perl"));
+        assert_eq!(head_language("#!/usr/local/bin/python3.11\n"), Some("Python"));
+        assert_eq!(head_language("#!/usr/bin/env frobnicate\nx\n"), None);
+    }
+
+    #[test]
+    fn modelines() {
+        assert_eq!(head_language("x = 1\n# vim: set ft=ruby:\n"), Some("Ruby"));
+        assert_eq!(head_language("# vi: filetype=sh\n"), Some("Shell"));
+        assert_eq!(head_language("# -*- mode: python -*-\n"), Some("Python"));
+        assert_eq!(head_language("# -*- perl -*-\n"), Some("Perl"));
+        assert_eq!(head_language("a\nb\nc\nd\ne\n# vim: ft=ruby\n"), None);
+    }
+}
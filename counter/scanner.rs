@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+use memchr::memchr;
+
+use super::MAX_CAPACITY;
+
+/// Content split into lines, handed out as byte slices without their `\n` or
+/// `\r\n` terminator. Lines borrow from the source, so nothing is allocated
+/// per line.
+pub trait Lines {
+    /// The start of the content, used to detect the language from a `#!`
+    /// line or modeline. Does not consume anything.
+    fn head(&mut self) -> io::Result<&[u8]>;
+
+    fn next_line(&mut self) -> io::Result<Option<&[u8]>>;
+
+    /// Everything not yet handed out as a line.
+    fn rest(&mut self) -> io::Result<Cow<'_, [u8]>>;
+}
+
+/// Reads lines through a buffer of `MAX_CAPACITY` bytes, which grows only
+/// for longer lines.
+pub struct LineScanner<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// Unread bytes are `buf[start..end]`.
+    start: usize,
+    end: usize,
+    eof: bool,
+}
+
+impl<R: Read> LineScanner<R> {
+    /// Reads from `reader` into `buf`, which may be a buffer left by an
+    /// earlier scanner (see [`LineScanner::into_buffer`]).
+    pub fn new(reader: R, mut buf: Vec<u8>) -> Self {
+        if buf.len() < MAX_CAPACITY {
+            buf.resize(MAX_CAPACITY, 0);
+        }
+        LineScanner {
+            reader,
+            buf,
+            start: 0,
+            end: 0,
+            eof: false,
+        }
+    }
+
+    /// Gives back the buffer for reuse.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Moves the unread bytes to the front of the buffer, growing it if it
+    /// is full, and reads more after them.
+    fn fill(&mut self) -> io::Result<()> {
+        self.buf.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        if self.end == self.buf.len() {
+            self.buf.resize(self.buf.len() * 2, 0);
+        }
+        let read = loop {
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        if read == 0 {
+            self.eof = true;
+        } else {
+            self.end += read;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Lines for LineScanner<R> {
+    fn head(&mut self) -> io::Result<&[u8]> {
+        if self.start == self.end && !self.eof {
+            self.fill()?;
+        }
+        Ok(&self.buf[self.start..self.end])
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<&[u8]>> {
+        let line = loop {
+            if let Some(pos) = memchr(b'\n', &self.buf[self.start..self.end]) {
+                let line = self.start..self.start + pos;
+                self.start += pos + 1;
+                break line;
+            }
+            if self.eof {
+                if self.start == self.end {
+                    return Ok(None);
+                }
+                let line = self.start..self.end;
+                self.start = self.end;
+                break line;
+            }
+            self.fill()?;
+        };
+        Ok(Some(strip_cr(&self.buf[line])))
+    }
+
+    fn rest(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        let mut rest = self.buf[self.start..self.end].to_vec();
+        self.start = self.end;
+        if !self.eof {
+            self.reader.read_to_end(&mut rest)?;
+            self.eof = true;
+        }
+        Ok(Cow::Owned(rest))
+    }
+}
+
+/// Lines of content that is already in memory, e.g. a mapped file.
+pub struct SliceLines<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> SliceLines<'a> {
+    pub fn new(content: &'a [u8]) -> Self {
+        SliceLines { rest: content }
+    }
+}
+
+impl Lines for SliceLines<'_> {
+    fn head(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.rest[..self.rest.len().min(MAX_CAPACITY)])
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.rest.is_empty() {
+            return Ok(None);
+        }
+        let (line, rest) = match memchr(b'\n', self.rest) {
+            Some(pos) => (&self.rest[..pos], &self.rest[pos + 1..]),
+            None => (self.rest, &self.rest[self.rest.len()..]),
+        };
+        self.rest = rest;
+        Ok(Some(strip_cr(line)))
+    }
+
+    fn rest(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        let rest = self.rest;
+        self.rest = &[];
+        Ok(Cow::Borrowed(rest))
+    }
+}
+
+fn strip_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
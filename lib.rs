@@ -0,0 +1,208 @@
+//! Count lines of code, blank lines and comments per file type.
+//!
+//! ```no_run
+//! use steps_rust::{count_paths, Config};
+//!
+//! let config = Config::new().exclude("target/").max_depth(8);
+//! let result = count_paths(&["src"], &config).unwrap();
+//! for info in &result.info {
+//!     println!("{}: {} lines of code", info.filetype, info.code);
+//! }
+//! ```
+
+#[path = "counter/counter.rs"]
+pub mod counter;
+#[path = "report/report.rs"]
+pub mod report;
+#[path = "walker/walker.rs"]
+pub mod walker;
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+pub use counter::{
+    count_bytes, count_file, count_reader, CntResult, CountOptions, FileInfo, FileRecord, Source, SCHEMA_VERSION,
+};
+pub use report::{Output, Report, SortKey};
+pub use walker::{WalkOptions, Walker};
+
+/// Settings for [`count_paths`], built with chained setters.
+#[derive(Default, Debug, Clone)]
+pub struct Config {
+    walk: WalkOptions,
+    count: CountOptions,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    /// Do not descend more than `depth` directories below each path.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.walk.max_depth = Some(depth);
+        self
+    }
+
+    /// Follow symbolic links; symlink cycles are skipped.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.walk.follow_symlinks = follow;
+        self
+    }
+
+    /// Do not respect `.gitignore`, `.ignore`, `.stepsignore` and extra
+    /// ignore files.
+    pub fn no_ignore(mut self, no_ignore: bool) -> Self {
+        self.walk.no_ignore = no_ignore;
+        self
+    }
+
+    /// Read additional ignore patterns from `file`.
+    pub fn ignore_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.walk.ignore_files.push(file.into());
+        self
+    }
+
+    /// Skip files and directories matching the gitignore-style `glob`.
+    pub fn exclude(mut self, glob: impl Into<String>) -> Self {
+        self.walk.excludes.push(glob.into());
+        self
+    }
+
+    /// Only count files matching the gitignore-style `glob`. May be given
+    /// several times.
+    pub fn include(mut self, glob: impl Into<String>) -> Self {
+        self.walk.includes.push(glob.into());
+        self
+    }
+
+    /// Keep per-file records in [`CntResult::files`].
+    pub fn keep_files(mut self, keep: bool) -> Self {
+        self.count.keep_files = keep;
+        self
+    }
+
+    /// Count with `threads` worker threads instead of one per available
+    /// core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.count.threads = Some(threads);
+        self
+    }
+
+    pub fn walk_options(&self) -> &WalkOptions {
+        &self.walk
+    }
+
+    pub fn count_options(&self) -> &CountOptions {
+        &self.count
+    }
+}
+
+/// Number of walked paths that may wait for a counting worker before the
+/// walk pauses, keeping memory flat on huge trees.
+const QUEUE_CAPACITY: usize = 4096;
+
+/// Walks `paths` (files or directories) and counts the lines of every file
+/// found. Counting starts while the walk is still in progress. Unreadable
+/// entries below a path are skipped and noted in [`CntResult::warnings`]; a
+/// missing or unreadable path itself is an error.
+pub fn count_paths<P: AsRef<str>>(paths: &[P], config: &Config) -> io::Result<CntResult> {
+    let roots: Vec<String> = paths.iter().map(|path| path.as_ref().to_string()).collect();
+    let input_path = roots.join(" ");
+    let walker = Walker::new(config.walk.clone());
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_CAPACITY);
+
+    let walk = thread::spawn(move || -> io::Result<Vec<String>> {
+        let mut warnings = Vec::new();
+        for root in &roots {
+            let mut emit = |file| {
+                // Only fails once counting has stopped.
+                let _ = sender.send(file);
+            };
+            walker
+                .walk_with(root, &mut emit, &mut warnings)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", root, err)))?;
+        }
+        Ok(warnings)
+    });
+
+    let mut result = counter::count_from(receiver, input_path, &config.count);
+    result.warnings.extend(walk.join().unwrap()?);
+    result.warnings.sort();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("steps-rust-{}-{}", name, std::process::id()));
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("src/main.rs"), "// main\nfn main() {}\n").unwrap();
+        fs::write(root.join("src/nested/util.py"), "def f():\n    return 1\n\n").unwrap();
+        fs::write(root.join("README.md"), "# Title\n\n```rust\nlet x = 1;\n```\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn count_paths_honors_the_config() {
+        let root = tree("config");
+        let path = root.to_string_lossy().to_string();
+        let all = count_paths(&[&path], &Config::new()).unwrap();
+        let shallow = count_paths(&[&path], &Config::new().max_depth(1)).unwrap();
+        let excluded = count_paths(&[&path], &Config::new().exclude("*.py")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!((all.all_files, shallow.all_files, excluded.all_files), (3, 1, 2));
+        assert_eq!(all.input_path, path);
+    }
+
+    #[test]
+    fn keep_files_records_every_file_by_path() {
+        let root = tree("keep-files");
+        let path = root.to_string_lossy().to_string();
+        let kept = count_paths(&[&path], &Config::new().keep_files(true)).unwrap();
+        let dropped = count_paths(&[&path], &Config::new()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let paths: Vec<&str> = kept.files.iter().map(|record| record.path.as_str()).collect();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths, sorted);
+        assert!(kept.files.iter().all(|record| record.info.files == 1));
+        assert!(dropped.files.is_empty());
+    }
+
+    #[test]
+    fn count_paths_matches_counting_the_walked_files() {
+        let root = tree("pipeline");
+        let path = root.to_string_lossy().to_string();
+        let config = Config::new().keep_files(true);
+        let streamed = count_paths(&[&path], &config).unwrap();
+        let files = Walker::new(WalkOptions::default()).walk(&path, &mut Vec::new()).unwrap();
+        let listed = counter::count(files, path.clone(), config.count_options());
+        let single = count_paths(&[&path], &config.clone().threads(1)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(streamed.all_files, 3);
+        assert_eq!(streamed.to_json(), listed.to_json());
+        assert_eq!(single.to_json(), listed.to_json());
+    }
+
+    #[test]
+    fn missing_root_is_an_error() {
+        let root = tree("missing-root");
+        let path = root.to_string_lossy().to_string();
+        let missing = root.join("missing").to_string_lossy().to_string();
+        let err = count_paths(&[&path, &missing], &Config::new()).unwrap_err();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with(&missing));
+    }
+}
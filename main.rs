@@ -0,0 +1,97 @@
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use steps_rust::{count_paths, Config, Report};
+
+const USAGE: &str = "Usage: steps-rust [OPTIONS] <PATH>...
+
+Count lines of code, blanks and comments in the given files and directories.
+
+Options:
+      --max-depth <N>    Do not descend more than N directories below each path
+  -L, --follow           Follow symbolic links (symlink cycles are skipped)
+      --exclude <GLOB>   Skip files and directories matching GLOB (repeatable)
+      --include <GLOB>   Only count files matching GLOB (repeatable)
+      --ignore-file <F>  Read additional ignore patterns from F (repeatable)
+      --no-ignore        Do not respect .gitignore, .ignore and .stepsignore files
+      --mixed            Show lines holding both code and comments separately
+      --by-file          Report every file instead of totals per file type
+      --sort <COLUMN>    Sort rows by name, files, steps (default), code, blanks,
+                         comments, docs, mixed or bytes
+      --top <N>          Only show the first N rows
+      --output <FORMAT>  Output format: table (default), json, yaml, toml, csv, tsv,
+                         markdown or html
+      --no-totals        Leave out the totals row
+      --threads <N>      Count with N threads (default: number of CPUs)
+  -h, --help             Print this help and exit";
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let mut paths = Vec::new();
+    let mut config = Config::new();
+    let mut report = Report::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-L" | "--follow" => config = config.follow_symlinks(true),
+            "--max-depth" => config = config.max_depth(parse_value(&arg, args.next())),
+            "--exclude" => config = config.exclude(parse_value::<String>(&arg, args.next())),
+            "--include" => config = config.include(parse_value::<String>(&arg, args.next())),
+            "--ignore-file" => config = config.ignore_file(parse_value::<String>(&arg, args.next())),
+            "--no-ignore" => config = config.no_ignore(true),
+            "--mixed" => report.show_mixed = true,
+            "--by-file" => {
+                report.by_file = true;
+                config = config.keep_files(true);
+            }
+            "--sort" => report.sort = parse_value(&arg, args.next()),
+            "--top" => report.top = Some(parse_value(&arg, args.next())),
+            "--output" => report.output = parse_value(&arg, args.next()),
+            "--no-totals" => report.no_totals = true,
+            "--threads" => config = config.threads(parse_value(&arg, args.next())),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                eprintln!("Unknown option: {}\n\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    match count_paths(&paths, &config) {
+        Ok(result) => {
+            for warning in &result.warnings {
+                eprintln!("{}", warning);
+            }
+            let mut stdout = io::stdout().lock();
+            if let Err(err) = report.write(&result, &mut stdout).and_then(|_| stdout.flush()) {
+                eprintln!("Failed to write the report: {}", err);
+                process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("Failed to count lines: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> T {
+    let Some(value) = value else {
+        eprintln!("Missing value for {}\n\n{}", option, USAGE);
+        process::exit(2);
+    };
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid value for {}: {}", option, value);
+        process::exit(2);
+    })
+}
@@ -0,0 +1,56 @@
+use std::io::{self, Write};
+
+use crate::CntResult;
+
+use super::{Report, BLANKS, BYTES, CODE, COMMENTS, DOCS, FILES, MIXED, STEPS};
+
+/// Writes the rows as CSV (or TSV with a tab `delimiter`) for spreadsheets:
+/// a header, then every count including `mixed`. Embedded languages name the
+/// file type holding them in `embedded_in`; files note their encoding.
+pub fn write(report: &Report, result: &CntResult, delimiter: char, out: &mut dyn Write) -> io::Result<()> {
+    let columns = [FILES, STEPS, CODE, BLANKS, COMMENTS, DOCS, MIXED, BYTES];
+    let mut write_record = |fields: &[String]| {
+        let fields: Vec<String> = fields.iter().map(|field| quote_field(field, delimiter)).collect();
+        writeln!(out, "{}", fields.join(&delimiter.to_string()))
+    };
+
+    let labels = if report.by_file { ["path", "filetype"] } else { ["filetype", "embedded_in"] };
+    let mut header: Vec<String> = labels
+        .into_iter()
+        .chain(columns.iter().map(|(name, _)| *name))
+        .map(str::to_string)
+        .collect();
+    if report.by_file {
+        header.push("encoding".to_string());
+    }
+    write_record(&header)?;
+    for row in report.rows(result) {
+        let mut fields = vec![row.name];
+        fields.push(if report.by_file { row.info.filetype.clone() } else { row.parent.unwrap_or_default() });
+        fields.extend(columns.iter().map(|(_, value)| value(&row.info).to_string()));
+        if report.by_file {
+            fields.push(row.info.encoding.clone().unwrap_or_default());
+        }
+        write_record(&fields)?;
+    }
+    if !report.no_totals {
+        let totals = result.totals();
+        let mut fields = vec!["total".to_string(), String::new()];
+        fields.extend(columns.iter().map(|(_, value)| value(&totals).to_string()));
+        if report.by_file {
+            fields.push(String::new());
+        }
+        write_record(&fields)?;
+    }
+    Ok(())
+}
+
+/// Quotes `field` if it contains the delimiter, a quote or a line break,
+/// doubling any quotes, as spreadsheets expect.
+fn quote_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+
+use crate::CntResult;
+
+use super::{Report, Row};
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #1f2328; }
+table { border-collapse: collapse; }
+th, td { padding: 0.3em 0.8em; border-bottom: 1px solid #d0d7de; }
+th { cursor: pointer; user-select: none; text-align: left; }
+th[aria-sort=ascending]::after { content: ' \\25b2'; }
+th[aria-sort=descending]::after { content: ' \\25bc'; }
+td.count, th.count { text-align: right; font-variant-numeric: tabular-nums; }
+tr.embedded td:first-child { padding-left: 2em; color: #59636e; }
+tfoot td { font-weight: bold; border-bottom: none; }
+td.share { min-width: 12em; white-space: nowrap; }
+.bar { display: inline-block; max-width: 8em; height: 0.8em; margin-right: 0.5em; background: #0969da; vertical-align: middle; }
+";
+
+/// Sorts the row groups (`tbody` elements) by the clicked column of their
+/// first row, keeping embedded languages below their file type.
+const SCRIPT: &str = "
+document.querySelectorAll('#counts th').forEach((th, column) => {
+  th.addEventListener('click', () => {
+    const table = th.closest('table');
+    const numeric = th.classList.contains('count');
+    // Counts sort largest first on the first click, names alphabetically.
+    const current = th.getAttribute('aria-sort');
+    const ascending = current === 'none' ? !numeric : current === 'descending';
+    table.querySelectorAll('th').forEach(other => other.setAttribute('aria-sort', 'none'));
+    th.setAttribute('aria-sort', ascending ? 'ascending' : 'descending');
+    const key = body => {
+      const cell = body.rows[0].cells[column];
+      return numeric ? Number(cell.dataset.value) : cell.textContent;
+    };
+    const bodies = Array.from(table.tBodies);
+    bodies.sort((a, b) => {
+      const order = numeric ? key(a) - key(b) : key(a).localeCompare(key(b));
+      return ascending ? order : -order;
+    });
+    bodies.forEach(body => table.insertBefore(body, table.tFoot));
+  });
+});
+";
+
+/// Writes a standalone HTML page holding the rows of the terminal table,
+/// with columns sortable by clicking their header and a bar showing each
+/// row's share of all lines.
+pub fn write(report: &Report, result: &CntResult, out: &mut dyn Write) -> io::Result<()> {
+    let columns = report.columns();
+    let title = format!("Line counts: {}", result.input_path);
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&title))?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", escape(&title))?;
+    writeln!(out, "<table id=\"counts\">")?;
+
+    write!(out, "<thead><tr><th aria-sort=\"none\">{}</th>", report.name_header())?;
+    if report.by_file {
+        write!(out, "<th aria-sort=\"none\">filetype</th>")?;
+    }
+    for (name, _) in &columns {
+        write!(out, "<th class=\"count\" aria-sort=\"none\">{}</th>", name)?;
+    }
+    writeln!(out, "<th class=\"count\" aria-sort=\"none\">share</th></tr></thead>")?;
+
+    // One `tbody` per top-level row, so that sorting moves embedded languages
+    // along with their file type.
+    let rows = report.rows(result);
+    let mut open = false;
+    for row in &rows {
+        if row.parent.is_none() {
+            if open {
+                writeln!(out, "</tbody>")?;
+            }
+            writeln!(out, "<tbody>")?;
+            open = true;
+        }
+        write_row(report, row, result.all_steps, out)?;
+    }
+    if open {
+        writeln!(out, "</tbody>")?;
+    }
+
+    if !report.no_totals {
+        let totals = result.totals();
+        write!(out, "<tfoot><tr><td>total</td>")?;
+        if report.by_file {
+            write!(out, "<td></td>")?;
+        }
+        for (_, value) in &columns {
+            write!(out, "<td class=\"count\">{}</td>", value(&totals))?;
+        }
+        writeln!(out, "<td></td></tr></tfoot>")?;
+    }
+    writeln!(out, "</table>")?;
+    writeln!(out, "<script>{}</script>", SCRIPT)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn write_row(report: &Report, row: &Row, all_steps: usize, out: &mut dyn Write) -> io::Result<()> {
+    let info = &row.info;
+    match row.parent {
+        Some(_) => write!(out, "<tr class=\"embedded\"><td>{}</td>", escape(&row.name))?,
+        None => write!(out, "<tr><td>{}</td>", escape(&row.name))?,
+    }
+    if report.by_file {
+        write!(out, "<td>{}</td>", escape(&info.filetype))?;
+    }
+    for (_, value) in report.columns() {
+        let value = value(info);
+        write!(out, "<td class=\"count\" data-value=\"{}\">{}</td>", value, value)?;
+    }
+    let share = if all_steps == 0 {
+        0.0
+    } else {
+        info.steps as f64 * 100.0 / all_steps as f64
+    };
+    writeln!(
+        out,
+        "<td class=\"share\" data-value=\"{}\"><span class=\"bar\" style=\"width: {:.2}em\"></span>{:.1}%</td></tr>",
+        info.steps,
+        share * 0.08,
+        share
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
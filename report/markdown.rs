@@ -0,0 +1,58 @@
+use std::io::{self, Write};
+
+use crate::CntResult;
+
+use super::Report;
+
+/// Writes the table as a GitHub-flavored Markdown table, with the same rows
+/// and columns as the terminal table.
+pub fn write(report: &Report, result: &CntResult, out: &mut dyn Write) -> io::Result<()> {
+    let columns = report.columns();
+    let mut header = vec![report.name_header()];
+    let mut align = vec![":---"];
+    if report.by_file {
+        header.push("filetype");
+        align.push(":---");
+    }
+    for (name, _) in &columns {
+        header.push(name);
+        align.push("---:");
+    }
+    writeln!(out, "| {} |", header.join(" | "))?;
+    writeln!(out, "| {} |", align.join(" | "))?;
+
+    for row in report.rows(result) {
+        let mut cells = match row.parent {
+            Some(_) => vec![format!("\u{2003}\u{21b3} {}", escape(&row.name))],
+            None => vec![escape(&row.name)],
+        };
+        if report.by_file {
+            cells.push(escape(&row.info.filetype));
+        }
+        cells.extend(columns.iter().map(|(_, value)| value(&row.info).to_string()));
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+    if !report.no_totals {
+        let totals = result.totals();
+        let mut cells = vec!["**total**".to_string()];
+        if report.by_file {
+            cells.push(String::new());
+        }
+        cells.extend(columns.iter().map(|(_, value)| format!("**{}**", value(&totals))));
+        writeln!(out, "| {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+/// Escapes the characters that would break a table cell or be read as
+/// formatting.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,326 @@
+mod delimited;
+mod html;
+mod markdown;
+mod table;
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::{CntResult, FileInfo};
+
+/// How the results are presented: the terminal table, a serialized
+/// document, or a table for spreadsheets, Markdown or HTML.
+#[derive(Default)]
+pub struct Report {
+    pub show_mixed: bool,
+    pub by_file: bool,
+    pub sort: SortKey,
+    pub top: Option<usize>,
+    pub output: Output,
+    pub no_totals: bool,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Table,
+    /// The whole result, unsorted and untruncated; see `CntResult::to_json`.
+    Json,
+    /// The same document as `Json`.
+    Yaml,
+    /// The same document as `Json`.
+    Toml,
+    Csv,
+    Tsv,
+    Markdown,
+    Html,
+}
+
+impl FromStr for Output {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Output::Table),
+            "json" => Ok(Output::Json),
+            "yaml" | "yml" => Ok(Output::Yaml),
+            "toml" => Ok(Output::Toml),
+            "csv" => Ok(Output::Csv),
+            "tsv" => Ok(Output::Tsv),
+            "markdown" | "md" => Ok(Output::Markdown),
+            "html" => Ok(Output::Html),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Column to sort rows by. Counts sort in descending order, names in
+/// ascending order; ties are broken by name.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Files,
+    #[default]
+    Steps,
+    Code,
+    Blanks,
+    Comments,
+    Docs,
+    Mixed,
+    Bytes,
+}
+
+impl FromStr for SortKey {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "name" | "filetype" | "path" => SortKey::Name,
+            "files" => SortKey::Files,
+            "steps" | "lines" => SortKey::Steps,
+            "code" => SortKey::Code,
+            "blanks" => SortKey::Blanks,
+            "comments" => SortKey::Comments,
+            "docs" => SortKey::Docs,
+            "mixed" => SortKey::Mixed,
+            "bytes" => SortKey::Bytes,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl SortKey {
+    fn value(self, info: &FileInfo) -> usize {
+        match self {
+            SortKey::Name => 0,
+            SortKey::Files => info.files,
+            SortKey::Steps => info.steps,
+            SortKey::Code => info.code,
+            SortKey::Blanks => info.blanks,
+            SortKey::Comments => info.comments,
+            SortKey::Docs => info.doc_comments,
+            SortKey::Mixed => info.mixed,
+            SortKey::Bytes => info.bytes,
+        }
+    }
+
+    fn sort(self, rows: &mut [Row]) {
+        rows.sort_by(|a, b| {
+            self.value(&b.info)
+                .cmp(&self.value(&a.info))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+}
+
+/// A row of a report: a file type, an embedded language or, with
+/// `--by-file`, a single file.
+struct Row {
+    /// File type, or the path of the file.
+    name: String,
+    /// File type holding this embedded language.
+    parent: Option<String>,
+    info: FileInfo,
+}
+
+/// A count column: its header and how to read it from a row.
+type Column = (&'static str, fn(&FileInfo) -> usize);
+
+const FILES: Column = ("files", |i| i.files);
+const STEPS: Column = ("steps", |i| i.steps);
+const CODE: Column = ("code", |i| i.code);
+const BLANKS: Column = ("blanks", |i| i.blanks);
+const COMMENTS: Column = ("comments", |i| i.comments);
+const DOCS: Column = ("docs", |i| i.doc_comments);
+const MIXED: Column = ("mixed", |i| i.mixed);
+const BYTES: Column = ("bytes", |i| i.bytes);
+
+impl Report {
+    /// Writes `result` to `out` in the chosen format.
+    pub fn write(&self, result: &CntResult, out: &mut dyn Write) -> io::Result<()> {
+        match self.output {
+            Output::Table => table::write(self, result, out),
+            Output::Json => writeln!(out, "{}", result.to_json()),
+            Output::Yaml => write!(out, "{}", result.to_yaml()),
+            Output::Toml => write!(out, "{}", result.to_toml()),
+            Output::Csv => delimited::write(self, result, ',', out),
+            Output::Tsv => delimited::write(self, result, '\t', out),
+            Output::Markdown => markdown::write(self, result, out),
+            Output::Html => html::write(self, result, out),
+        }
+    }
+
+    /// Header of the name column.
+    fn name_header(&self) -> &'static str {
+        if self.by_file {
+            "path"
+        } else {
+            "filetype"
+        }
+    }
+
+    /// The count columns of the table, Markdown and HTML reports; `mixed`
+    /// only with `--mixed`.
+    fn columns(&self) -> Vec<Column> {
+        let mut columns = vec![FILES, STEPS, CODE, BLANKS, COMMENTS, DOCS];
+        if self.show_mixed {
+            columns.push(MIXED);
+        }
+        columns.push(BYTES);
+        columns
+    }
+
+    /// The sorted and truncated rows of the report, per file type or per
+    /// file.
+    fn rows(&self, result: &CntResult) -> Vec<Row> {
+        if self.by_file {
+            self.file_rows(result)
+        } else {
+            self.type_rows(result)
+        }
+    }
+
+    /// Rows per file type. Embedded languages are listed below the file type
+    /// holding them.
+    fn type_rows(&self, result: &CntResult) -> Vec<Row> {
+        let mut types: Vec<Row> = result
+            .info
+            .iter()
+            .map(|i| Row {
+                name: i.filetype.clone(),
+                parent: None,
+                info: i.clone(),
+            })
+            .collect();
+        self.sort.sort(&mut types);
+        types.truncate(self.top.unwrap_or(usize::MAX));
+
+        let mut rows = Vec::new();
+        for mut row in types {
+            let mut children: Vec<Row> = std::mem::take(&mut row.info.children)
+                .into_iter()
+                .map(|child| Row {
+                    name: child.filetype.clone(),
+                    parent: Some(row.name.clone()),
+                    info: child,
+                })
+                .collect();
+            self.sort.sort(&mut children);
+            rows.push(row);
+            rows.extend(children);
+        }
+        rows
+    }
+
+    /// Rows per file, counting embedded languages with their file.
+    fn file_rows(&self, result: &CntResult) -> Vec<Row> {
+        let mut rows: Vec<Row> = result
+            .files
+            .iter()
+            .map(|record| Row {
+                name: record.path.clone(),
+                parent: None,
+                info: record.info.total(),
+            })
+            .collect();
+        self.sort.sort(&mut rows);
+        rows.truncate(self.top.unwrap_or(usize::MAX));
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> CntResult {
+        let info = |filetype: &str, steps, code| FileInfo {
+            filetype: filetype.to_string(),
+            steps,
+            code,
+            blanks: steps - code,
+            files: 1,
+            bytes: steps * 10,
+            ..Default::default()
+        };
+        let mut html = info("HTML", 10, 8);
+        html.children.push(info("JavaScript", 4, 4));
+        let mut result = CntResult {
+            input_path: "src".to_string(),
+            info: vec![info("Rust", 20, 15), html, info("C, \"legacy\"", 5, 5)],
+            all_files: 3,
+            ..Default::default()
+        };
+        result.all_steps = result.info.iter().map(|info| info.total().steps).sum();
+        result
+    }
+
+    fn render(report: Report) -> String {
+        let mut out = Vec::new();
+        report.write(&sample(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn rows_are_sorted_with_children_below_parent() {
+        let report = Report::default();
+        let names: Vec<String> = report.rows(&sample()).into_iter().map(|row| row.name).collect();
+        assert_eq!(names, ["Rust", "HTML", "JavaScript", "C, \"legacy\""]);
+    }
+
+    #[test]
+    fn top_keeps_children_of_kept_rows() {
+        let report = Report {
+            sort: SortKey::Name,
+            top: Some(2),
+            ..Default::default()
+        };
+        let names: Vec<String> = report.rows(&sample()).into_iter().map(|row| row.name).collect();
+        assert_eq!(names, ["C, \"legacy\"", "HTML", "JavaScript"]);
+    }
+
+    #[test]
+    fn csv_quotes_fields_and_adds_totals() {
+        let csv = render(Report {
+            output: Output::Csv,
+            ..Default::default()
+        });
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "filetype,embedded_in,files,steps,code,blanks,comments,docs,mixed,bytes");
+        assert_eq!(lines[3], "JavaScript,HTML,1,4,4,0,0,0,0,40");
+        assert_eq!(lines[4], "\"C, \"\"legacy\"\"\",,1,5,5,0,0,0,0,50");
+        assert_eq!(lines[5], "total,,3,39,0,0,0,0,0,0");
+    }
+
+    #[test]
+    fn no_totals_leaves_out_the_totals_row() {
+        let tsv = render(Report {
+            output: Output::Tsv,
+            no_totals: true,
+            ..Default::default()
+        });
+        assert_eq!(tsv.lines().count(), 5);
+        assert!(!tsv.contains("total"));
+    }
+
+    #[test]
+    fn markdown_escapes_and_indents_children() {
+        let markdown = render(Report {
+            output: Output::Markdown,
+            ..Default::default()
+        });
+        assert!(markdown.starts_with("| filetype | files |"));
+        assert!(markdown.contains("| \u{2003}\u{21b3} JavaScript | 1 |"));
+        assert!(markdown.contains("| **total** |"));
+    }
+
+    #[test]
+    fn html_escapes_names() {
+        let html = render(Report {
+            output: Output::Html,
+            ..Default::default()
+        });
+        assert!(html.contains("<td>C, &quot;legacy&quot;</td>"));
+        assert_eq!(html.matches("<tbody>").count(), 3);
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::{self, Write};
+
+use crate::CntResult;
+
+use super::Report;
+
+/// Writes the plain-text table shown in the terminal.
+pub fn write(report: &Report, result: &CntResult, out: &mut dyn Write) -> io::Result<()> {
+    let columns = report.columns();
+    let mut header = vec![report.name_header()];
+    if report.by_file {
+        header.push("filetype");
+    }
+    header.extend(columns.iter().map(|(name, _)| name));
+    let labels = if report.by_file { 2 } else { 1 };
+
+    let rows: Vec<Vec<String>> = report
+        .rows(result)
+        .iter()
+        .map(|row| {
+            let mut cells = match row.parent {
+                Some(_) => vec![format!(" |- {}", row.name)],
+                None => vec![row.name.clone()],
+            };
+            if report.by_file {
+                cells.push(row.info.filetype.clone());
+            }
+            cells.extend(columns.iter().map(|(_, value)| value(&row.info).to_string()));
+            cells
+        })
+        .collect();
+    let totals = result.totals();
+    let mut total_row = vec!["total".to_string()];
+    total_row.resize(labels, String::new());
+    total_row.extend(columns.iter().map(|(_, value)| value(&totals).to_string()));
+
+    // Label columns are left-aligned and as wide as their longest cell, with
+    // a gap between them; counts are right-aligned in 12 columns.
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            if column >= labels {
+                return 12;
+            }
+            let longest = rows
+                .iter()
+                .chain([&total_row])
+                .map(|row| row[column].chars().count())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or(0);
+            if column + 1 < labels {
+                longest + 2
+            } else {
+                longest
+            }
+        })
+        .collect();
+    let rule = "-".repeat(widths.iter().sum());
+    let write_row = |out: &mut dyn Write, cells: &[String]| {
+        let line: String = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, &width))| {
+                if column < labels {
+                    format!("{:<width$}", cell)
+                } else {
+                    format!("{:>width$}", cell)
+                }
+            })
+            .collect();
+        writeln!(out, "{}", line)
+    };
+
+    writeln!(out, "input: {}", result.input_path)?;
+    writeln!(out, "{}", rule)?;
+    write_row(out, &header.iter().map(|h| h.to_string()).collect::<Vec<_>>())?;
+    writeln!(out, "{}", rule)?;
+    for row in &rows {
+        write_row(out, row)?;
+    }
+    if !report.no_totals {
+        writeln!(out, "{}", rule)?;
+        write_row(out, &total_row)?;
+    }
+    writeln!(out, "{}", rule)
+}
@@ -0,0 +1,423 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Ignore files read from every directory during traversal, lowest
+/// precedence first.
+pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", ".stepsignore"];
+
+/// A single gitignore-style pattern.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    segments: Vec<Vec<char>>,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl Pattern {
+    /// Parses one line of an ignore file. Returns `None` for blank lines and
+    /// comments.
+    pub fn parse(line: &str) -> Option<Pattern> {
+        let line = trim_trailing_spaces(line.trim_end_matches(['\n', '\r']));
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let line = line.strip_prefix('\\').filter(|rest| rest.starts_with(['!', '#'])).unwrap_or(line);
+
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return None;
+        }
+
+        // A slash anywhere but at the end anchors the pattern to the
+        // directory holding the ignore file; otherwise it matches at any depth.
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+
+        let mut segments: Vec<Vec<char>> = Vec::new();
+        if !anchored {
+            segments.push(vec!['*', '*']);
+        }
+        segments.extend(line.split('/').filter(|s| !s.is_empty()).map(|s| s.chars().collect()));
+
+        Some(Pattern {
+            segments,
+            negated,
+            dir_only,
+        })
+    }
+
+    /// Whether `path`, relative to the pattern's base directory and using `/`
+    /// as separator, matches this pattern (ignoring negation).
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let parts: Vec<Vec<char>> = path.split('/').map(|s| s.chars().collect()).collect();
+        match_segments(&self.segments, &parts)
+    }
+}
+
+/// The patterns of one ignore file together with the location of its base
+/// directory relative to the walk root.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<Pattern>,
+    /// Bytes to strip from a root-relative path to make it base-relative;
+    /// set for ignore files in the walk root's subdirectories.
+    strip: usize,
+    /// Prefix that turns a root-relative path into a base-relative one; set
+    /// for ignore files in directories above the walk root.
+    prefix: String,
+}
+
+impl IgnoreRules {
+    pub fn parse(content: &str) -> IgnoreRules {
+        IgnoreRules {
+            patterns: content.lines().filter_map(Pattern::parse).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Reads an ignore file. A missing file yields `Ok(None)`.
+    pub fn from_file(path: &Path) -> io::Result<Option<IgnoreRules>> {
+        match fs::read(path) {
+            Ok(content) => Ok(Some(IgnoreRules::parse(&String::from_utf8_lossy(&content)))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Scopes the rules to the subdirectory `dir` of the walk root.
+    pub fn below_root(mut self, dir: &str) -> IgnoreRules {
+        self.strip = if dir.is_empty() { 0 } else { dir.len() + 1 };
+        self
+    }
+
+    /// Scopes the rules to an ancestor directory of the walk root, `root` being
+    /// the root's path relative to that ancestor.
+    pub fn above_root(mut self, root: &str) -> IgnoreRules {
+        self.prefix = format!("{}/", root);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns `Some(true)` if the root-relative `path` is ignored,
+    /// `Some(false)` if it is explicitly re-included by a negated pattern and
+    /// `None` if no pattern matches. The last matching pattern wins.
+    pub fn matched(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let path = path.get(self.strip..)?;
+        let path = if self.prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}{}", self.prefix, path)
+        };
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(&path, is_dir))
+            .map(|pattern| !pattern.negated)
+    }
+}
+
+/// `--exclude` / `--include` globs, relative to the walk root.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    excludes: Vec<Pattern>,
+    includes: Vec<Pattern>,
+}
+
+impl Overrides {
+    pub fn new(excludes: &[String], includes: &[String]) -> Overrides {
+        Overrides {
+            excludes: excludes.iter().filter_map(|glob| Pattern::parse(glob)).collect(),
+            includes: includes.iter().filter_map(|glob| Pattern::parse(glob)).collect(),
+        }
+    }
+
+    /// Excluded entries are skipped entirely. When include globs are given,
+    /// only files matching one of them are kept; directories are always
+    /// descended into.
+    pub fn is_skipped(&self, path: &str, is_dir: bool) -> bool {
+        if self.excludes.iter().any(|pattern| pattern.matches(path, is_dir)) {
+            return true;
+        }
+        !is_dir && !self.includes.is_empty() && !self.includes.iter().any(|pattern| pattern.matches(path, is_dir))
+    }
+}
+
+fn trim_trailing_spaces(line: &str) -> &str {
+    let trimmed = line.trim_end_matches(' ');
+    if trimmed.len() < line.len() && trimmed.ends_with('\\') {
+        // "foo\ " keeps the escaped space.
+        &line[..trimmed.len() + 1]
+    } else {
+        trimmed
+    }
+}
+
+fn match_segments(segments: &[Vec<char>], parts: &[Vec<char>]) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return parts.is_empty();
+    };
+
+    if segment.as_slice() == ['*', '*'] {
+        // A trailing "/**" matches everything inside, but not the directory
+        // itself.
+        if rest.is_empty() {
+            return !parts.is_empty();
+        }
+        return (0..=parts.len()).any(|skip| match_segments(rest, &parts[skip..]));
+    }
+
+    match parts.split_first() {
+        Some((part, parts)) => match_wildcard(segment, part) && match_segments(rest, parts),
+        None => false,
+    }
+}
+
+/// Matches one path component against a pattern component supporting `*`,
+/// `?`, `[...]` classes and backslash escapes.
+fn match_wildcard(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    while p < pattern.len() && pattern[p] == '*' {
+                        p += 1;
+                    }
+                    backtrack = Some((p, t));
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, len)) = match_class(&pattern[p..], text[t]) {
+                        if matched {
+                            p += len;
+                            t += 1;
+                            continue;
+                        }
+                    } else if text[t] == '[' {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+                '\\' if p + 1 < pattern.len() && pattern[p + 1] == text[t] => {
+                    p += 2;
+                    t += 1;
+                    continue;
+                }
+                '\\' if p + 1 < pattern.len() => {}
+                c if c == text[t] => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        match backtrack {
+            Some((bp, bt)) => {
+                p = bp;
+                t = bt + 1;
+                backtrack = Some((bp, bt + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the character class starting at `pattern[0] == '['`.
+/// Returns whether it matched and the length of the class, or `None` if the
+/// class is not terminated (the `[` is then literal).
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let mut lo = pattern[i];
+        if lo == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if lo == '\\' && i + 1 < pattern.len() {
+            i += 1;
+            lo = pattern[i];
+        }
+
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&hi| hi != ']') {
+            let hi = pattern[i + 2];
+            matched |= lo <= c && c <= hi;
+            i += 3;
+        } else {
+            matched |= lo == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(rules: &IgnoreRules, path: &str, is_dir: bool) -> bool {
+        rules.matched(path, is_dir) == Some(true)
+    }
+
+    #[test]
+    fn unanchored_pattern_matches_at_any_depth() {
+        let rules = IgnoreRules::parse("*.log\n");
+        assert!(ignored(&rules, "debug.log", false));
+        assert!(ignored(&rules, "a/b/debug.log", false));
+        assert!(!ignored(&rules, "debug.txt", false));
+    }
+
+    #[test]
+    fn slash_anchors_pattern_to_base() {
+        let rules = IgnoreRules::parse("/build\ndocs/gen\n");
+        assert!(ignored(&rules, "build", true));
+        assert!(!ignored(&rules, "src/build", true));
+        assert!(ignored(&rules, "docs/gen", true));
+        assert!(!ignored(&rules, "src/docs/gen", true));
+    }
+
+    #[test]
+    fn trailing_slash_matches_directories_only() {
+        let rules = IgnoreRules::parse("target/\n");
+        assert!(ignored(&rules, "target", true));
+        assert!(!ignored(&rules, "target", false));
+    }
+
+    #[test]
+    fn negated_pattern_re_includes() {
+        let rules = IgnoreRules::parse("*.log\n!keep.log\n");
+        assert!(ignored(&rules, "debug.log", false));
+        assert_eq!(rules.matched("keep.log", false), Some(false));
+        assert_eq!(rules.matched("main.rs", false), None);
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let rules = IgnoreRules::parse("!keep.log\n*.log\n");
+        assert!(ignored(&rules, "keep.log", false));
+    }
+
+    #[test]
+    fn escaped_bang_and_hash_are_literal() {
+        let rules = IgnoreRules::parse("\\!important\n\\#notes\n# comment\n");
+        assert!(ignored(&rules, "!important", false));
+        assert!(ignored(&rules, "#notes", false));
+        assert!(!ignored(&rules, "comment", false));
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_directories() {
+        let rules = IgnoreRules::parse("a/**/z\n");
+        assert!(ignored(&rules, "a/z", false));
+        assert!(ignored(&rules, "a/b/z", false));
+        assert!(ignored(&rules, "a/b/c/z", false));
+        assert!(!ignored(&rules, "b/z", false));
+    }
+
+    #[test]
+    fn leading_double_star_matches_everywhere() {
+        let rules = IgnoreRules::parse("**/fixtures\n");
+        assert!(ignored(&rules, "fixtures", true));
+        assert!(ignored(&rules, "tests/data/fixtures", true));
+    }
+
+    #[test]
+    fn trailing_double_star_matches_contents_not_directory() {
+        let rules = IgnoreRules::parse("vendor/**\n");
+        assert!(ignored(&rules, "vendor/lib.rs", false));
+        assert!(ignored(&rules, "vendor/a/b.rs", false));
+        assert!(!ignored(&rules, "vendor", true));
+    }
+
+    #[test]
+    fn wildcards_do_not_cross_slashes() {
+        let rules = IgnoreRules::parse("src/*.rs\nfile?.txt\n");
+        assert!(ignored(&rules, "src/main.rs", false));
+        assert!(!ignored(&rules, "src/bin/main.rs", false));
+        assert!(ignored(&rules, "file1.txt", false));
+        assert!(!ignored(&rules, "file10.txt", false));
+    }
+
+    #[test]
+    fn character_classes() {
+        let rules = IgnoreRules::parse("log[0-9].txt\nx[!ab]\ny[^c]\n[]]z\n");
+        assert!(ignored(&rules, "log5.txt", false));
+        assert!(!ignored(&rules, "logx.txt", false));
+        assert!(ignored(&rules, "xc", false));
+        assert!(!ignored(&rules, "xa", false));
+        assert!(!ignored(&rules, "yc", false));
+        assert!(ignored(&rules, "yd", false));
+        assert!(ignored(&rules, "]z", false));
+    }
+
+    #[test]
+    fn unterminated_class_is_literal() {
+        let rules = IgnoreRules::parse("a[b\n");
+        assert!(ignored(&rules, "a[b", false));
+        assert!(!ignored(&rules, "ab", false));
+    }
+
+    #[test]
+    fn escaped_trailing_space_is_kept() {
+        let rules = IgnoreRules::parse("name\\ \nother  \n");
+        assert!(ignored(&rules, "name ", false));
+        assert!(ignored(&rules, "other", false));
+    }
+
+    #[test]
+    fn nested_rules_apply_below_their_directory() {
+        let rules = IgnoreRules::parse("/gen\n*.tmp\n").below_root("sub/dir");
+        assert!(ignored(&rules, "sub/dir/gen", true));
+        assert!(ignored(&rules, "sub/dir/x/a.tmp", false));
+        assert!(!ignored(&rules, "gen", true));
+    }
+
+    #[test]
+    fn rules_above_root_see_root_relative_paths() {
+        let rules = IgnoreRules::parse("/project/out\n").above_root("project");
+        assert!(ignored(&rules, "out", true));
+        assert!(!ignored(&rules, "src/out", true));
+    }
+
+    #[test]
+    fn overrides_exclude_and_include() {
+        let overrides = Overrides::new(&["tests/".to_string()], &["*.rs".to_string()]);
+        assert!(overrides.is_skipped("tests", true));
+        assert!(!overrides.is_skipped("src", true));
+        assert!(!overrides.is_skipped("src/main.rs", false));
+        assert!(overrides.is_skipped("README.md", false));
+    }
+}
@@ -0,0 +1,374 @@
+mod ignore;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ignore::{IgnoreRules, Overrides, IGNORE_FILE_NAMES};
+
+#[derive(Default, Debug, Clone)]
+pub struct WalkOptions {
+    /// Maximum directory depth to descend into. The input path itself is
+    /// depth 0, its direct children depth 1. `None` means no limit.
+    pub max_depth: Option<usize>,
+    /// Follow symbolic links to files and directories. Symlinks are skipped
+    /// otherwise, except for the input path itself.
+    pub follow_symlinks: bool,
+    /// Disable `.gitignore`, `.ignore`, `.stepsignore` and `ignore_files`
+    /// handling. `excludes` and `includes` still apply.
+    pub no_ignore: bool,
+    /// Additional gitignore-style files applied relative to each input path,
+    /// with lower precedence than the ignore files found while walking.
+    pub ignore_files: Vec<PathBuf>,
+    /// Gitignore-style globs, relative to each input path, of entries to skip.
+    pub excludes: Vec<String>,
+    /// Gitignore-style globs, relative to each input path; when non-empty only
+    /// files matching one of them are kept.
+    pub includes: Vec<String>,
+}
+
+pub struct Walker {
+    options: WalkOptions,
+    overrides: Overrides,
+}
+
+struct WalkState<'a> {
+    /// Canonical paths of the directories being visited, used to detect
+    /// symlink cycles. Only maintained when following symlinks.
+    ancestors: Vec<PathBuf>,
+    /// Ignore rules in effect, lowest precedence first.
+    rules: Vec<IgnoreRules>,
+    emit: &'a mut dyn FnMut(String),
+    warnings: &'a mut Vec<String>,
+}
+
+impl Walker {
+    pub fn new(options: WalkOptions) -> Self {
+        let overrides = Overrides::new(&options.excludes, &options.includes);
+        Walker { options, overrides }
+    }
+
+    /// Expands `input_path` into the list of files below it, in a stable
+    /// (sorted) order. Entries below the input path that cannot be read are
+    /// skipped and noted in `warnings`; only a missing or unreadable input
+    /// path is an error.
+    pub fn walk(&self, input_path: &str, warnings: &mut Vec<String>) -> io::Result<Vec<String>> {
+        let mut files = Vec::new();
+        self.walk_with(input_path, &mut |file| files.push(file), warnings)?;
+        Ok(files)
+    }
+
+    /// Like [`Walker::walk`], but hands each file to `emit` as soon as it is
+    /// found.
+    pub fn walk_with(
+        &self,
+        input_path: &str,
+        emit: &mut dyn FnMut(String),
+        warnings: &mut Vec<String>,
+    ) -> io::Result<()> {
+        let root = Path::new(input_path);
+        let metadata = fs::metadata(root)?;
+        if !metadata.is_dir() {
+            emit(input_path.to_string());
+            return Ok(());
+        }
+
+        let mut state = WalkState {
+            ancestors: Vec::new(),
+            rules: Vec::new(),
+            emit,
+            warnings,
+        };
+        if self.options.follow_symlinks {
+            state.ancestors.push(fs::canonicalize(root)?);
+        }
+        if !self.options.no_ignore {
+            state.rules = self.root_rules(root, state.warnings)?;
+        }
+        self.visit_dir(root, "", 1, &mut state)
+    }
+
+    /// Collects the rules that apply from outside the walked tree: the
+    /// `ignore_files` option and the ignore files of the directories between
+    /// the enclosing git repository's top level and `root`.
+    fn root_rules(&self, root: &Path, warnings: &mut Vec<String>) -> io::Result<Vec<IgnoreRules>> {
+        let mut rules = Vec::new();
+        for path in &self.options.ignore_files {
+            match IgnoreRules::from_file(path)? {
+                Some(file_rules) => rules.push(file_rules),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("ignore file {} not found", path.display()),
+                    ))
+                }
+            }
+        }
+
+        let root = fs::canonicalize(root)?;
+        if root.join(".git").exists() {
+            return Ok(rules);
+        }
+        let Some(top_level) = root.ancestors().skip(1).find(|dir| dir.join(".git").exists()) else {
+            return Ok(rules);
+        };
+
+        let mut dirs: Vec<&Path> = root.ancestors().skip(1).take_while(|dir| *dir != top_level).collect();
+        dirs.push(top_level);
+        for dir in dirs.into_iter().rev() {
+            let relative = root.strip_prefix(dir).unwrap_or(&root).to_string_lossy().replace('\\', "/");
+            for name in IGNORE_FILE_NAMES {
+                match IgnoreRules::from_file(&dir.join(name)) {
+                    Ok(Some(file_rules)) if !file_rules.is_empty() => rules.push(file_rules.above_root(&relative)),
+                    Ok(_) => {}
+                    Err(err) => warnings.push(format!("Failed to read {}: {}", dir.join(name).display(), err)),
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    fn visit_dir(&self, dir: &Path, rel_dir: &str, depth: usize, state: &mut WalkState) -> io::Result<()> {
+        if self.options.max_depth.is_some_and(|max| depth > max) {
+            return Ok(());
+        }
+
+        // An entry that cannot be read is skipped; the rest of the directory
+        // is still walked.
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(err) => state.warnings.push(format!("Failed to read an entry of {}: {}", dir.display(), err)),
+            }
+        }
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let rules_len = state.rules.len();
+        if !self.options.no_ignore {
+            for name in IGNORE_FILE_NAMES {
+                match IgnoreRules::from_file(&dir.join(name)) {
+                    Ok(Some(rules)) if !rules.is_empty() => state.rules.push(rules.below_root(rel_dir)),
+                    Ok(_) => {}
+                    Err(err) => state.warnings.push(format!("Failed to read {}: {}", dir.join(name).display(), err)),
+                }
+            }
+        }
+
+        for entry in entries {
+            let path = entry.path();
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(err) => {
+                    state.warnings.push(format!("Failed to read {}: {}", path.display(), err));
+                    continue;
+                }
+            };
+
+            let is_dir = if file_type.is_symlink() {
+                if !self.options.follow_symlinks {
+                    continue;
+                }
+                match fs::metadata(&path) {
+                    Ok(metadata) => metadata.is_dir(),
+                    Err(err) => {
+                        state.warnings.push(format!("Failed to follow symlink {}: {}", path.display(), err));
+                        continue;
+                    }
+                }
+            } else {
+                file_type.is_dir()
+            };
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel_path = if rel_dir.is_empty() {
+                name
+            } else {
+                format!("{}/{}", rel_dir, name)
+            };
+            if self.is_skipped(&rel_path, is_dir, &state.rules) {
+                continue;
+            }
+
+            if !is_dir {
+                (state.emit)(path.to_string_lossy().to_string());
+                continue;
+            }
+
+            // Cycles are only possible through symlinks, so the ancestor
+            // chain is tracked only when they are followed.
+            if self.options.follow_symlinks {
+                let canonical = match fs::canonicalize(&path) {
+                    Ok(canonical) => canonical,
+                    Err(err) => {
+                        state.warnings.push(format!("Failed to read {}: {}", path.display(), err));
+                        continue;
+                    }
+                };
+                if state.ancestors.contains(&canonical) {
+                    state.warnings.push(format!("Skipping symlink cycle at {}", path.display()));
+                    continue;
+                }
+                state.ancestors.push(canonical);
+            }
+
+            if let Err(err) = self.visit_dir(&path, &rel_path, depth + 1, state) {
+                state.warnings.push(format!("Failed to read {}: {}", path.display(), err));
+            }
+
+            if self.options.follow_symlinks {
+                state.ancestors.pop();
+            }
+        }
+
+        state.rules.truncate(rules_len);
+        Ok(())
+    }
+
+    fn is_skipped(&self, rel_path: &str, is_dir: bool, rules: &[IgnoreRules]) -> bool {
+        if self.overrides.is_skipped(rel_path, is_dir) {
+            return true;
+        }
+        if self.options.no_ignore {
+            return false;
+        }
+        if is_dir && rel_path.rsplit('/').next() == Some(".git") {
+            return true;
+        }
+        rules.iter().rev().find_map(|rules| rules.matched(rel_path, is_dir)) == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("steps-rust-walk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Walks `root` and returns the files found, relative to `root`, and the
+    /// warnings.
+    fn walk(root: &Path, options: WalkOptions) -> (Vec<String>, Vec<String>) {
+        let mut warnings = Vec::new();
+        let files = Walker::new(options).walk(&root.to_string_lossy(), &mut warnings).unwrap();
+        let files = files
+            .iter()
+            .map(|file| Path::new(file).strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        (files, warnings)
+    }
+
+    fn with_depth(max_depth: Option<usize>) -> WalkOptions {
+        WalkOptions {
+            max_depth,
+            ..Default::default()
+        }
+    }
+
+    fn following(follow_symlinks: bool) -> WalkOptions {
+        WalkOptions {
+            follow_symlinks,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn files_are_sorted() {
+        let root = temp_dir("sorted");
+        for name in ["b.rs", "a.rs", "C.rs", "sub/z.rs", "sub/m.rs"] {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let (files, warnings) = walk(&root, WalkOptions::default());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(files, ["C.rs", "a.rs", "b.rs", "sub/m.rs", "sub/z.rs"]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn max_depth_limits_descent() {
+        let root = temp_dir("depth");
+        fs::create_dir_all(root.join("one/two")).unwrap();
+        for name in ["top.rs", "one/mid.rs", "one/two/deep.rs"] {
+            fs::write(root.join(name), "").unwrap();
+        }
+        let depth0 = walk(&root, with_depth(Some(0))).0;
+        let depth1 = walk(&root, with_depth(Some(1))).0;
+        let unlimited = walk(&root, with_depth(None)).0;
+        let file = Walker::new(with_depth(Some(0)))
+            .walk(&root.join("top.rs").to_string_lossy(), &mut Vec::new())
+            .unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(depth0.is_empty());
+        assert_eq!(depth1, ["top.rs"]);
+        assert_eq!(unlimited, ["one/mid.rs", "one/two/deep.rs", "top.rs"]);
+        assert_eq!(file.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_followed_only_when_asked() {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_dir("symlinks");
+        let outside = temp_dir("symlinks-target");
+        fs::create_dir_all(outside.join("dir")).unwrap();
+        fs::write(outside.join("file.rs"), "").unwrap();
+        fs::write(outside.join("dir/inner.rs"), "").unwrap();
+        fs::write(root.join("own.rs"), "").unwrap();
+        symlink(outside.join("file.rs"), root.join("file_link.rs")).unwrap();
+        symlink(outside.join("dir"), root.join("dir_link")).unwrap();
+
+        let skipped = walk(&root, following(false)).0;
+        let followed = walk(&root, following(true)).0;
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+
+        assert_eq!(skipped, ["own.rs"]);
+        assert_eq!(followed, ["dir_link/inner.rs", "file_link.rs", "own.rs"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle_is_walked_once() {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_dir("cycle");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("top.rs"), "").unwrap();
+        fs::write(root.join("sub/inner.rs"), "").unwrap();
+        symlink("..", root.join("sub/up")).unwrap();
+
+        let (files, warnings) = walk(&root, following(true));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(files, ["sub/inner.rs", "top.rs"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Skipping symlink cycle"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_entries_are_skipped() {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_dir("unreadable");
+        fs::write(root.join("a.rs"), "").unwrap();
+        fs::write(root.join("z.rs"), "").unwrap();
+        symlink(root.join("missing"), root.join("m_dangling")).unwrap();
+
+        let (files, warnings) = walk(&root, following(true));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(files, ["a.rs", "z.rs"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Failed to follow symlink"));
+    }
+}